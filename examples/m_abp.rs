@@ -0,0 +1,189 @@
+use rddsv::channel::*;
+use rddsv::lts::*;
+use rddsv::process::*;
+use rddsv::search::*;
+use std::fmt;
+
+/* messages the sender hands over */
+const N: i32 = 3;
+
+#[derive(Default, std::fmt::Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SharedVars {
+    pub delivered: i32, // messages the receiver passed on.
+    pub bad: bool,      // one was passed on twice or out of order.
+}
+
+impl fmt::Display for SharedVars {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "delivered={} bad={}", self.delivered, self.bad)
+    }
+}
+
+/* the alternating bit, for the sender the next message and for the
+ * receiver the last one taken
+ */
+#[derive(Default, std::fmt::Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LocalVars {
+    pub bit: i32,
+    pub n: i32,
+    pub m: Msg,
+}
+
+impl Local for LocalVars {
+    fn show(&self) -> String {
+        format!("(b={} n={} m={})", self.bit, self.n, self.m)
+    }
+}
+
+fn always(_: &Ctx, _: &SharedVars, _: &LocalVars) -> bool {
+    true
+}
+
+fn nop(_: &Ctx, _: &mut SharedVars, _: &mut LocalVars) {}
+
+/* a data message carries the message number and the bit */
+fn frame(_: &Ctx, _: &SharedVars, l: &LocalVars) -> Msg {
+    l.n * 2 + l.bit
+}
+
+/* an acknowledgement of the current bit moves on to the next message */
+fn acked(_: &Ctx, _: &mut SharedVars, l: &mut LocalVars, m: Msg) {
+    if l.n < N && m == l.bit {
+        l.n += 1;
+        l.bit ^= 1;
+    }
+}
+
+fn sender_def() -> Process<SharedVars, LocalVars> {
+    let send = ProcessTrans::new_local("send", 1, |_: &Ctx, _, l: &LocalVars| l.n < N, nop)
+        .put("data", frame);
+    let done = ProcessTrans::new_local("done", 2, |_: &Ctx, _, l: &LocalVars| l.n == N, nop);
+    let ack = |dst| ProcessTrans::new_local("ack", dst, always, nop).get("ack", acked);
+    /* nothing came back in time, send again */
+    let timeout = ProcessTrans::new_local("timeout", 0, always, nop);
+
+    /* acks are taken whenever they come, so the receiver never waits on a
+     * full ack channel for good
+     */
+    let s0 = ExecUnit::new(0, vec![send, done, ack(0)]);
+    let s1 = ExecUnit::new(1, vec![ack(0), timeout]);
+    let s2 = ExecUnit::new_end(2, vec![ack(2)]);
+    Process::new("S", vec![s0, s1, s2])
+}
+
+fn keep(_: &Ctx, _: &mut SharedVars, l: &mut LocalVars, m: Msg) {
+    l.m = m;
+}
+
+/* pass the message on when it carries the expected bit, or whatever it
+ * carries when `checked` is off
+ */
+fn receiver_def(checked: bool) -> Process<SharedVars, LocalVars> {
+    let recv = ProcessTrans::new_local("recv", 1, always, nop).get("data", keep);
+    let ack = ProcessTrans::new_local(
+        "ack",
+        0,
+        always,
+        move |_: &Ctx, v: &mut SharedVars, l: &mut LocalVars| {
+            if !checked || l.m % 2 == l.bit {
+                v.bad |= l.m / 2 != v.delivered;
+                v.delivered += 1;
+                l.bit ^= 1;
+            }
+        },
+    )
+    .put("ack", |_: &Ctx, _, l: &LocalVars| l.m % 2);
+
+    let r0 = ExecUnit::new_end(0, vec![recv]);
+    let r1 = ExecUnit::new(1, vec![ack]);
+    Process::new("R", vec![r0, r1])
+}
+
+fn processes_def(checked: bool) -> Vec<Process<SharedVars, LocalVars>> {
+    vec![sender_def(), receiver_def(checked)]
+}
+
+/* both directions lose messages */
+fn lossy_state() -> State<SharedVars, LocalVars> {
+    let r: SharedVars = Default::default();
+    State::new(r)
+        .channel(Queue::new("data", 2).lossy())
+        .channel(Queue::new("ack", 2).lossy())
+}
+
+fn inv_in_order(s: &State<SharedVars, LocalVars>) -> bool {
+    !s.shared_vars.bad
+}
+
+pub fn main() {
+    let config = SearchConfig::new().invariant("in order", inv_in_order);
+    let lts = concurrent_composition_with(processes_def(true), lossy_state(), &config);
+    lts.visualize("res/m_abp.dot");
+    println!("{} states", lts.len());
+    for (f, v) in lts.faults() {
+        println!("{} at {}", f, v);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lossy() {
+        let config = SearchConfig::new().invariant("in order", inv_in_order);
+        let lts = concurrent_composition_with(processes_def(true), lossy_state(), &config);
+        assert!(lts.faults().is_empty());
+        assert!(lts.deadlocks().is_empty());
+
+        /* every message may get through */
+        let done = lts.detect_termination();
+        assert!(!done.is_empty());
+        assert!(done
+            .iter()
+            .all(|d| lts.state(*d).unwrap().shared_vars.delivered == N));
+        assert!(lts.trans().iter().any(|t| t.label == "S.send data!0 lost"));
+
+        /* a receiver ignoring the bit passes a resent message on twice,
+         * and goes on counting without bound
+         */
+        let config = config.stop_on_error(true);
+        let lts = concurrent_composition_with(processes_def(false), lossy_state(), &config);
+        assert_eq!(lts.faults()[0].0, Fault::Invariant("in order".to_string()));
+        let path = &lts.faults()[0].1.path;
+        assert_eq!(
+            path.iter().filter(|t| t.label == "R.recv data?0").count(),
+            2
+        );
+    }
+
+    #[test]
+    fn queue() {
+        let r: SharedVars = Default::default();
+        let s = State::new(r)
+            .channel(Queue::new("data", 1))
+            .channel(Queue::new("ack", 1));
+        let lts = concurrent_composition(processes_def(true), s);
+
+        /* no loss, so the first send is taken as is */
+        assert_eq!(lts.trans()[0].label, "S.send data!0");
+        let q = &lts.state(1).unwrap().channels[0];
+        assert_eq!((q.to_string(), q.is_full()), ("data=[0]".to_string(), true));
+        assert!(lts.trans().iter().all(|t| !t.label.ends_with("lost")));
+        assert!(lts.check_invariant(inv_in_order).is_empty());
+    }
+
+    #[test]
+    fn reorder() {
+        /* the alternating bit relies on the order the channels keep */
+        let r: SharedVars = Default::default();
+        let s = State::new(r)
+            .channel(Queue::new("data", 2).reorder())
+            .channel(Queue::new("ack", 2).reorder());
+        let config = SearchConfig::new()
+            .invariant("in order", inv_in_order)
+            .stop_on_error(true);
+        let lts = concurrent_composition_with(processes_def(true), s, &config);
+        assert_eq!(lts.faults().len(), 1);
+    }
+}
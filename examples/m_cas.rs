@@ -1,107 +1,112 @@
+use rddsv::codec::*;
+use rddsv::ltl::*;
 use rddsv::lts::*;
 use rddsv::process::*;
+use rddsv::search::*;
 use std::fmt;
 
 #[derive(Default, std::fmt::Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct SharedVars {
-    pub x: i32,  // shared variables between P and Q.
-    pub t1: i32, // P's local variables.
-    pub t2: i32, // Q's local variables.
+    pub x: i32, // shared variables between P and Q.
 }
 
 impl fmt::Display for SharedVars {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmt, "x={} t1={} t2={}", self.x, self.t1, self.t2)
+        write!(fmt, "x={}", self.x)
     }
 }
 
-/* User definition of guard and action */
-fn action_p_cas(_prop: Prop, p: &mut SharedVars, q: &SharedVars) {
-    p.x = 1;
-    p.t1 = q.x;
-}
-
-fn guard_p_retry(_prop: Prop, p: SharedVars) -> bool {
-    p.t1 == 1
-}
-
-fn action_p_retry(_prop: Prop, p: &mut SharedVars, _q: &SharedVars) {
-    p.t1 = 0;
-}
-
-fn guard_p_begin(_prop: Prop, p: SharedVars) -> bool {
-    p.t1 == 0
-}
-
-fn action_p_unlock(_prop: Prop, p: &mut SharedVars, _q: &SharedVars) {
-    p.x = 0;
-}
+impl Codec for SharedVars {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.x.encode(out);
+    }
 
-fn action_q_cas(_prop: Prop, q: &mut SharedVars, p: &SharedVars) {
-    q.x = 1;
-    q.t2 = p.x;
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(SharedVars {
+            x: i32::decode(input)?,
+        })
+    }
 }
 
-fn guard_q_retry(_prop: Prop, q: SharedVars) -> bool {
-    q.t2 == 1
+/* the register each of P and Q keeps to itself */
+#[derive(Default, std::fmt::Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LocalVars {
+    pub t: i32,
 }
 
-fn action_q_retry(_prop: Prop, q: &mut SharedVars, _p: &SharedVars) {
-    q.t2 = 0;
+impl Local for LocalVars {
+    fn show(&self) -> String {
+        format!("(t={})", self.t)
+    }
 }
 
-fn guard_q_begin(_prop: Prop, q: SharedVars) -> bool {
-    q.t2 == 0
-}
+impl Codec for LocalVars {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.t.encode(out);
+    }
 
-fn action_q_unlock(_prop: Prop, q: &mut SharedVars, _p: &SharedVars) {
-    q.x = 0;
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(LocalVars {
+            t: i32::decode(input)?,
+        })
+    }
 }
 
-fn m_cas_p_def() -> Process<SharedVars> {
-    /* Create Process "P" */
-    let p_cas = ProcessTrans::new("CAS", 1, guard_true, action_p_cas);
-    let p_retry = ProcessTrans::new("retry", 0, guard_p_retry, action_p_retry);
-    let p_begin = ProcessTrans::new("begin", 2, guard_p_begin, action_nop);
-    let p_end = ProcessTrans::new("end", 3, guard_true, action_nop);
-    let p_unlock = ProcessTrans::new("unlock", 0, guard_true, action_p_unlock);
-
-    let p0 = ExecUnit::new(0, vec![p_cas]);
-    let p1 = ExecUnit::new(1, vec![p_retry, p_begin]);
-    let p2 = ExecUnit::new(2, vec![p_end]);
-    let p3 = ExecUnit::new(3, vec![p_unlock]);
+/* User definition of guard and action, shared by P and Q */
+fn cas_def(label: &str) -> Process<SharedVars, LocalVars> {
+    /* Create a CAS worker */
+    let cas = ProcessTrans::new_local(
+        "CAS",
+        1,
+        |_: &Ctx, _: &SharedVars, _: &LocalVars| true,
+        |_: &Ctx, v: &mut SharedVars, l: &mut LocalVars| {
+            l.t = v.x;
+            v.x = 1;
+        },
+    );
+    let retry = ProcessTrans::new_local(
+        "retry",
+        0,
+        |_: &Ctx, _: &SharedVars, l: &LocalVars| l.t == 1,
+        |_: &Ctx, _: &mut SharedVars, l: &mut LocalVars| l.t = 0,
+    );
+    let begin = ProcessTrans::new_local(
+        "begin",
+        2,
+        |_: &Ctx, _: &SharedVars, l: &LocalVars| l.t == 0,
+        |_: &Ctx, _: &mut SharedVars, _: &mut LocalVars| {},
+    )
+    .progress();
+    let end = ProcessTrans::new("end", 3, guard_true, action_nop);
+    let unlock = ProcessTrans::new_closure(
+        "unlock",
+        0,
+        |_: &Ctx, _: &SharedVars| true,
+        |_: &Ctx, v: &mut SharedVars| v.x = 0,
+    );
+
+    let p0 = ExecUnit::new(0, vec![cas]);
+    let p1 = ExecUnit::new(1, vec![retry, begin]);
+    let p2 = ExecUnit::new(2, vec![end]);
+    let p3 = ExecUnit::new(3, vec![unlock]);
 
     let p = vec![p0, p1, p2, p3];
-    let ret = Process::new("P", p);
-
-    ret
+    Process::new(label, p)
 }
 
-fn m_cas_q_def() -> Process<SharedVars> {
-    /* Create Process "Q" */
-    let q_cas = ProcessTrans::new("CAS", 1, guard_true, action_q_cas);
-    let q_retry = ProcessTrans::new("retry", 0, guard_q_retry, action_q_retry);
-    let q_begin = ProcessTrans::new("begin", 2, guard_q_begin, action_nop);
-    let q_end = ProcessTrans::new("end", 3, guard_true, action_nop);
-    let q_unlock = ProcessTrans::new("unlock", 0, guard_true, action_q_unlock);
-
-    let q0 = ExecUnit::new(0, vec![q_cas]);
-    let q1 = ExecUnit::new(1, vec![q_retry, q_begin]);
-    let q2 = ExecUnit::new(2, vec![q_end]);
-    let q3 = ExecUnit::new(3, vec![q_unlock]);
-
-    let q = vec![q0, q1, q2, q3];
-    let ret = Process::new("Q", q);
-
-    ret
+/* the registers move along with P and Q, x is left as it is */
+fn no_locals(v: &SharedVars, _perm: &[usize]) -> SharedVars {
+    *v
 }
 
-fn m_cas_def() -> Vec<Process<SharedVars>> {
-    let p = m_cas_p_def();
-    let q = m_cas_q_def();
-    let process = vec![p, q];
+/* P and Q are never in the critical section at the same time */
+fn inv_mutex(s: &State<SharedVars, LocalVars>) -> bool {
+    let cs = Location::new(2);
+    !(s.locations[0] == cs && s.locations[1] == cs)
+}
 
-    process
+fn m_cas_def() -> Vec<Process<SharedVars, LocalVars>> {
+    vec![cas_def("P"), cas_def("Q")]
 }
 
 pub fn main() {
@@ -111,14 +116,42 @@ pub fn main() {
 
     let r: SharedVars = Default::default();
     let s = State::new(r);
-    let lts = concurrent_composition(process, s);
+    let mut lts = concurrent_composition(process, s);
     lts.visualize("res/m_cas.dot");
+    for v in lts.check_invariant(inv_mutex) {
+        println!("mutual exclusion violated at {}", v);
+    }
+
+    let livelock = lts.detect_livelock();
+    for l in &livelock {
+        println!("livelock at {}", l);
+    }
+    lts.mark_lasso(&livelock);
+    lts.visualize("res/m_cas_livelock.dot");
+
+    for f in &["G !(P@2 && Q@2)", "G(P@2 -> F P@0)"] {
+        let ltl = Ltl::parse(f).unwrap();
+        match lts.check_ltl(&ltl, &[]).unwrap() {
+            None => println!("{} holds", f),
+            Some(l) => println!("{} violated by {}", f, l),
+        }
+    }
+
+    /* P and Q are interchangeable */
+    let config = SearchConfig::new().symmetric(&["P", "Q"], no_locals);
+    let sym = concurrent_composition_with(m_cas_def(), State::new(r), &config);
+    println!("{} states, {} up to symmetry", lts.len(), sym.len());
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use file_diff::diff_files;
+    use rddsv::checkpoint::*;
+    use rddsv::ctl::*;
+    use rddsv::liveness::*;
+    use rddsv::parallel::*;
+    use rddsv::store::*;
     use std::fs::*;
 
     #[test]
@@ -165,4 +198,307 @@ mod test {
             println!("! {:?}", why.kind());
         });
     }
+
+    #[test]
+    fn invariant() {
+        let process = m_cas_def();
+        let r: SharedVars = Default::default();
+        let s = State::new(r);
+        let lts = concurrent_composition(process, s);
+
+        assert!(lts.check_invariant(inv_mutex).is_empty());
+    }
+
+    #[test]
+    fn livelock() {
+        let process = m_cas_def();
+        let r: SharedVars = Default::default();
+        let s = State::new(r);
+        let lts = concurrent_composition(process, s);
+        let l = lts.detect_livelock();
+
+        assert_eq!(l.len(), 6);
+        let prefix: Vec<&str> = l[0].prefix.iter().map(|t| t.label.as_str()).collect();
+        let cycle: Vec<&str> = l[0].cycle.iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(prefix, vec!["P.CAS"]);
+        assert_eq!(cycle, vec!["Q.CAS", "Q.retry"]);
+    }
+
+    #[test]
+    fn ltl() {
+        let process = m_cas_def();
+        let r: SharedVars = Default::default();
+        let s = State::new(r);
+        let lts = concurrent_composition(process, s);
+
+        let mutex = Ltl::parse("G !(P@2 && Q@2)").unwrap();
+        assert!(lts.check_ltl(&mutex, &[]).unwrap().is_none());
+
+        /* Q may spin on the lock forever while P waits in the critical section */
+        let leave = Ltl::parse("G(P@2 -> F P@0)").unwrap();
+        let l = lts.check_ltl(&leave, &[]).unwrap().unwrap();
+        let cycle: Vec<&str> = l.cycle.iter().map(|t| t.label.as_str()).collect();
+        assert!(cycle.iter().all(|t| t.starts_with("Q.")));
+
+        let unknown = Ltl::parse("G R@0").unwrap();
+        assert!(lts.check_ltl(&unknown, &[]).is_err());
+    }
+
+    #[test]
+    fn ctl() {
+        let process = m_cas_def();
+        let r: SharedVars = Default::default();
+        let s = State::new(r);
+        let lts = concurrent_composition(process, s);
+
+        let home = Ctl::parse("AG EF (P@0 && Q@0)").unwrap();
+        assert!(lts.check_ctl(&home, &[]).unwrap().contains(&0));
+
+        /* unlike EF, AF needs every path to get there */
+        let enter = Ctl::parse("AF P@2").unwrap();
+        assert!(!lts.check_ctl(&enter, &[]).unwrap().contains(&0));
+        let maybe = Ctl::parse("E(!Q@2 U P@2)").unwrap();
+        assert!(lts.check_ctl(&maybe, &[]).unwrap().contains(&0));
+    }
+
+    #[test]
+    fn fairness() {
+        let process = m_cas_def();
+        let r: SharedVars = Default::default();
+        let s = State::new(r);
+        let lts = concurrent_composition(process, s);
+        let wf = Fairness::new().weak("P").weak("Q");
+
+        /* spinning on a lock held by a process that never moves is unfair */
+        assert!(lts.detect_livelock_fair(&wf).unwrap().is_empty());
+        let leave = Ltl::parse("G(P@2 -> F P@0)").unwrap();
+        assert!(lts.check_ltl_fair(&leave, &[], &wf).unwrap().is_none());
+
+        /* P can still lose every CAS to Q */
+        let enter = Ltl::parse("G(P@1 -> F P@2)").unwrap();
+        let sf = wf.strong("P.begin");
+        let l = lts.check_ltl_fair(&enter, &[], &sf).unwrap().unwrap();
+        assert!(l.cycle.iter().any(|t| t.label == "Q.unlock"));
+        assert!(l.cycle.iter().all(|t| t.label != "P.begin"));
+
+        assert!(lts
+            .detect_livelock_fair(&Fairness::new().weak("R"))
+            .is_err());
+    }
+
+    #[test]
+    fn starvation() {
+        let process = m_cas_def();
+        let r: SharedVars = Default::default();
+        let s = State::new(r);
+        let lts = concurrent_composition(process, s);
+        let wf = Fairness::new().weak("P").weak("Q");
+
+        /* P is always enabled, so under weak fairness it keeps moving ... */
+        assert!(lts.detect_starvation("P", &[], &wf).unwrap().is_empty());
+        /* ... but it may spin between CAS and retry forever */
+        let l = lts.detect_starvation("P", &[0, 1], &wf).unwrap();
+        assert!(!l.is_empty());
+        assert!(l[0].cycle.iter().any(|t| t.label == "Q.begin"));
+    }
+
+    #[test]
+    fn parallel() {
+        let config = SearchConfig::new().threads(4);
+        let r: SharedVars = Default::default();
+        let lts = concurrent_composition_parallel(m_cas_def(), State::new(r), &config);
+        assert_eq!(lts.len(), 15);
+        assert_eq!(lts.trans().len(), 30);
+        assert_eq!(lts.detect_livelock().len(), 6);
+
+        /* renumbered, the result is the sequential one */
+        let config = config.canonical(true);
+        let lts = concurrent_composition_parallel(m_cas_def(), State::new(r), &config);
+        lts.visualize("res/test_m_cas_parallel.dot");
+
+        let mut file1 = match File::open("./res/test_m_cas_parallel.dot") {
+            Ok(f) => f,
+            Err(e) => panic!("{}", e),
+        };
+        let mut file2 = match File::open("./ref/m_cas.dot") {
+            Ok(f) => f,
+            Err(e) => panic!("{}", e),
+        };
+
+        assert!(diff_files(&mut file1, &mut file2), "They are different.");
+
+        std::fs::remove_file("res/test_m_cas_parallel.dot").unwrap_or_else(|why| {
+            println!("! {:?}", why.kind());
+        });
+    }
+
+    #[test]
+    fn symmetry() {
+        let r: SharedVars = Default::default();
+        let full = concurrent_composition(m_cas_def(), State::new(r));
+        let config = SearchConfig::new().symmetric(&["P", "Q"], no_locals);
+        let lts = concurrent_composition_with(m_cas_def(), State::new(r), &config);
+        assert_eq!(lts.len(), 8);
+        assert!(lts.check_invariant(inv_mutex).is_empty());
+        assert_eq!(
+            lts.detect_livelock().is_empty(),
+            full.detect_livelock().is_empty()
+        );
+    }
+
+    #[test]
+    fn locals() {
+        let r: SharedVars = Default::default();
+        let lts = concurrent_composition(m_cas_def(), State::new(r));
+
+        /* a process in its critical section won the CAS with its own register */
+        for i in 0..lts.len() {
+            let s = lts.state(i).unwrap();
+            for pid in 0..2 {
+                if s.locations[pid] == Location::new(2) {
+                    assert_eq!(s.local(pid).t, 0);
+                }
+            }
+        }
+        let s = lts.state(5).unwrap();
+        assert_eq!((s.local(0).t, s.local(1).t), (1, 0));
+
+        /* a process may start from registers of its own */
+        let process = vec![cas_def("P").with_init(LocalVars { t: 1 }), cas_def("Q")];
+        let lts = concurrent_composition(process, State::new(r));
+        assert_eq!(lts.state(0).unwrap().local(0).t, 1);
+        assert!(lts.check_invariant(inv_mutex).is_empty());
+    }
+
+    #[test]
+    fn store() {
+        let r: SharedVars = Default::default();
+        let c = coverage(m_cas_def(), State::new(r), &SearchConfig::new());
+        assert_eq!((c.states, c.trans), (15, 30));
+        assert_eq!(c.omission, 0.0);
+
+        /* a roomy table misses nothing, but cannot promise it */
+        let config = SearchConfig::new().store(Store::Bitstate {
+            log2_bits: 20,
+            hashes: 3,
+        });
+        let c = coverage(m_cas_def(), State::new(r), &config);
+        assert_eq!(c.states, 15);
+        assert!(c.omission > 0.0 && c.omission < 1e-9);
+
+        /* a table too large to allocate is cut down to 2^32 bits */
+        let config = SearchConfig::new().store(Store::Bitstate {
+            log2_bits: 64,
+            hashes: 3,
+        });
+        let c = coverage(m_cas_def(), State::new(r), &config);
+        assert_eq!(c.states, 15);
+        assert!(c.omission < 1e-20);
+
+        let config = SearchConfig::new().store(Store::Compact { bits: 32 });
+        let c = coverage(m_cas_def(), State::new(r), &config);
+        assert_eq!(c.states, 15);
+        assert!(c.omission > 0.0 && c.omission < 1e-6);
+
+        /* an 8 bit table fills up and drops states */
+        let config = SearchConfig::new().store(Store::Bitstate {
+            log2_bits: 3,
+            hashes: 2,
+        });
+        let c = coverage(m_cas_def(), State::new(r), &config);
+        assert!(c.states < 15);
+        assert!(c.omission > 0.1);
+    }
+
+    #[test]
+    fn checkpoint() {
+        let r: SharedVars = Default::default();
+        let path = std::env::temp_dir().join(format!("rddsv_m_cas_{}.ckpt", std::process::id()));
+        let ck = Checkpoint::new(&path, 4);
+
+        /* the snapshot left behind is one from the middle of the run */
+        let config = SearchConfig::new();
+        let lts =
+            concurrent_composition_checkpoint(m_cas_def(), State::new(r), &config, &ck).unwrap();
+        assert_eq!(lts.len(), 15);
+        let lts = resume(m_cas_def(), &config, &ck).unwrap();
+        lts.visualize("res/test_m_cas_resume.dot");
+
+        let mut file1 = match File::open("./res/test_m_cas_resume.dot") {
+            Ok(f) => f,
+            Err(e) => panic!("{}", e),
+        };
+        let mut file2 = match File::open("./ref/m_cas.dot") {
+            Ok(f) => f,
+            Err(e) => panic!("{}", e),
+        };
+
+        assert!(diff_files(&mut file1, &mut file2), "They are different.");
+
+        std::fs::remove_file("res/test_m_cas_resume.dot").unwrap_or_else(|why| {
+            println!("! {:?}", why.kind());
+        });
+
+        /* the order of a depth first frontier survives too */
+        let config = SearchConfig::new().strategy(Strategy::RandomDfs(3));
+        let full = concurrent_composition_with(m_cas_def(), State::new(r), &config);
+        concurrent_composition_checkpoint(m_cas_def(), State::new(r), &config, &ck).unwrap();
+        let lts = resume(m_cas_def(), &config, &ck).unwrap();
+        let edges = |l: &Lts<SharedVars, LocalVars>| -> Vec<(usize, usize)> {
+            l.trans().iter().map(|t| (t.before, t.after)).collect()
+        };
+        assert_eq!(edges(&lts), edges(&full));
+
+        /* a snapshot naming a state it does not hold is refused */
+        let mut bytes = std::fs::read(&path).unwrap();
+        let mut none = Vec::new();
+        0usize.encode(&mut none);
+        assert!(bytes.ends_with(&none));
+        bytes.truncate(bytes.len() - none.len());
+        1usize.encode(&mut bytes);
+        0u8.encode(&mut bytes);
+        999usize.encode(&mut bytes);
+        std::fs::write(&path, bytes).unwrap();
+        let err = resume(m_cas_def(), &config, &ck).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        std::fs::remove_file(&path).unwrap_or_else(|why| {
+            println!("! {:?}", why.kind());
+        });
+    }
+    /* states that hold the lock come first */
+    fn held(s: &State<SharedVars, LocalVars>) -> i64 {
+        -(s.shared_vars.x as i64)
+    }
+
+    #[test]
+    fn checkpoint_foreign() {
+        let r: SharedVars = Default::default();
+        let path =
+            std::env::temp_dir().join(format!("rddsv_m_cas_foreign_{}.ckpt", std::process::id()));
+        let ck = Checkpoint::new(&path, 4);
+        let config = SearchConfig::new().strategy(Strategy::BestFirst(held));
+        concurrent_composition_checkpoint(m_cas_def(), State::new(r), &config, &ck).unwrap();
+
+        /* a breadth first search would never take the states of the heap */
+        let err = resume(m_cas_def(), &SearchConfig::new(), &ck)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        /* nor do the states hold the queue a changed P puts to */
+        let mut process = m_cas_def();
+        let t = process[0].v[0].transs.remove(0);
+        let t = t.put("c", |_: &Ctx, _: &SharedVars, _: &LocalVars| 0);
+        process[0].v[0].transs.insert(0, t);
+        let err = resume(process, &config, &ck).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+        let lts = resume(m_cas_def(), &config, &ck).unwrap();
+        assert_eq!(lts.len(), 15);
+
+        std::fs::remove_file(&path).unwrap_or_else(|why| {
+            println!("! {:?}", why.kind());
+        });
+    }
 }
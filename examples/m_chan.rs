@@ -0,0 +1,128 @@
+use rddsv::lts::*;
+use rddsv::process::*;
+use std::fmt;
+
+#[derive(Default, std::fmt::Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SharedVars {
+    pub served: i32, // requests the server has answered.
+}
+
+impl fmt::Display for SharedVars {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "served={}", self.served)
+    }
+}
+
+/* the last value a process received */
+#[derive(Default, std::fmt::Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LocalVars {
+    pub m: Msg,
+}
+
+impl Local for LocalVars {
+    fn show(&self) -> String {
+        format!("(m={})", self.m)
+    }
+}
+
+fn always(_: &Ctx, _: &SharedVars, _: &LocalVars) -> bool {
+    true
+}
+
+fn nop(_: &Ctx, _: &mut SharedVars, _: &mut LocalVars) {}
+
+fn keep(_: &Ctx, _: &mut SharedVars, l: &mut LocalVars, m: Msg) {
+    l.m = m;
+}
+
+fn count(_: &Ctx, v: &mut SharedVars, _: &mut LocalVars) {
+    v.served += 1;
+}
+
+/* a client sends its prop plus one and waits for the double of it */
+fn client_def(prop: Prop) -> Vec<ExecUnit<SharedVars, LocalVars>> {
+    let ask =
+        ProcessTrans::new_local("ask", 1, always, nop).send("req", |c: &Ctx, _, _| c.prop + 1);
+    let get = ProcessTrans::new_local("get", 2, always, nop).recv("resp", keep);
+
+    let p0 = ExecUnit::new(0, vec![ask]);
+    let p1 = ExecUnit::new(1, vec![get]);
+    let p2 = ExecUnit::new_end(2, vec![])
+        .assert_local(move |_: &Ctx, _: &SharedVars, l: &LocalVars| l.m == 2 * (prop + 1));
+    vec![p0, p1, p2]
+}
+
+/* the server answers one request at a time */
+fn server_def() -> Process<SharedVars, LocalVars> {
+    let take = ProcessTrans::new_local("take", 1, always, nop).recv("req", keep);
+    let reply = ProcessTrans::new_local("reply", 0, always, count)
+        .send("resp", |_: &Ctx, _, l: &LocalVars| 2 * l.m);
+
+    let s0 = ExecUnit::new_end(0, vec![take]);
+    let s1 = ExecUnit::new(1, vec![reply]);
+    Process::new("S", vec![s0, s1])
+}
+
+fn processes_def(clients: usize) -> Vec<Process<SharedVars, LocalVars>> {
+    let mut process = Process::instances("C", clients, client_def);
+    process.push(server_def());
+    process
+}
+
+pub fn main() {
+    let r: SharedVars = Default::default();
+    let lts = concurrent_composition(processes_def(2), State::new(r));
+    lts.visualize("res/m_chan.dot");
+    println!("{} states, {} deadlocks", lts.len(), lts.deadlocks().len());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rddsv::search::*;
+
+    #[test]
+    fn rendezvous() {
+        let r: SharedVars = Default::default();
+        let lts = concurrent_composition(processes_def(1), State::new(r));
+
+        /* the send and the receive are one step each way */
+        assert_eq!(lts.len(), 3);
+        let labels: Vec<&str> = lts.trans().iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(labels, vec!["C0.ask|S.take req!1", "S.reply|C0.get resp!2"]);
+        let t = &lts.trans()[1];
+        assert_eq!(
+            (t.process, t.partners.clone(), t.msg),
+            (1, vec![0], Some(2))
+        );
+        assert!(t.involves(0) && t.involves(1));
+
+        let done = lts.detect_termination();
+        assert_eq!(done.len(), 1);
+        let s = lts.state(done[0]).unwrap();
+        assert_eq!((s.shared_vars.served, s.local(0).m), (1, 2));
+    }
+
+    #[test]
+    fn clients() {
+        /* every client gets its own answer, in either order */
+        let r: SharedVars = Default::default();
+        let config = SearchConfig::new().deadlock(true);
+        let lts = concurrent_composition_with(processes_def(2), State::new(r), &config);
+        assert!(lts.faults().is_empty());
+        let done = lts.detect_termination();
+        assert_eq!(done.len(), 2);
+        assert!(done
+            .iter()
+            .all(|d| lts.state(*d).unwrap().shared_vars.served == 2));
+
+        /* with no server a send waits forever */
+        let lts = concurrent_composition_with(
+            Process::instances("C", 2, client_def),
+            State::new(r),
+            &config,
+        );
+        assert_eq!(lts.len(), 1);
+        assert_eq!(lts.faults()[0].0, Fault::Deadlock);
+    }
+}
@@ -0,0 +1,152 @@
+use rddsv::lts::*;
+use rddsv::process::*;
+use std::fmt;
+
+#[derive(Default, std::fmt::Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SharedVars {
+    pub sum: i32, // inputs added so far.
+}
+
+impl fmt::Display for SharedVars {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "sum={}", self.sum)
+    }
+}
+
+/* the input a process read */
+#[derive(Default, std::fmt::Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LocalVars {
+    pub x: i32,
+}
+
+impl Local for LocalVars {
+    fn show(&self) -> String {
+        format!("(x={})", self.x)
+    }
+}
+
+fn always(_: &Ctx, _: &SharedVars, _: &LocalVars) -> bool {
+    true
+}
+
+fn nop(_: &Ctx, _: &mut SharedVars, _: &mut LocalVars) {}
+
+/* a process reads any input in 0..3 and adds it to the sum */
+fn reader_def(_prop: Prop) -> Vec<ExecUnit<SharedVars, LocalVars>> {
+    let read = ProcessTrans::new_local("read", 1, always, nop).choose(
+        &[0, 1, 2],
+        |_: &Ctx, _: &mut SharedVars, l: &mut LocalVars, x| l.x = x,
+    );
+    let add = ProcessTrans::new_local(
+        "add",
+        2,
+        always,
+        |_: &Ctx, v: &mut SharedVars, l: &mut LocalVars| v.sum += l.x,
+    );
+
+    let p0 = ExecUnit::new(0, vec![read]);
+    let p1 = ExecUnit::new(1, vec![add]);
+    let p2 = ExecUnit::new_end(2, vec![]);
+    vec![p0, p1, p2]
+}
+
+/* the environment may wipe out a positive sum once, or leave it be */
+fn havoc_def() -> Process<SharedVars, LocalVars> {
+    let havoc = ProcessTrans::new_choice(
+        "havoc",
+        1,
+        always,
+        |_: &Ctx, v: &SharedVars, l: &LocalVars| {
+            let mut outcomes = vec![("(keep)".to_string(), *v, *l)];
+            if v.sum > 0 {
+                outcomes.push(("(clear)".to_string(), SharedVars { sum: 0 }, *l));
+            }
+            outcomes
+        },
+    );
+
+    let e0 = ExecUnit::new(0, vec![havoc]);
+    let e1 = ExecUnit::new_end(1, vec![]);
+    Process::new("E", vec![e0, e1])
+}
+
+fn processes_def() -> Vec<Process<SharedVars, LocalVars>> {
+    Process::instances("P", 2, reader_def)
+}
+
+pub fn main() {
+    let r: SharedVars = Default::default();
+    let lts = concurrent_composition(processes_def(), State::new(r));
+    lts.visualize("res/m_choice.dot");
+
+    let sums: Vec<i32> = lts
+        .detect_termination()
+        .iter()
+        .map(|d| lts.state(*d).unwrap().shared_vars.sum)
+        .collect();
+    println!("{} states, sums {:?}", lts.len(), sums);
+
+    let mut process = processes_def();
+    process.push(havoc_def());
+    let lts = concurrent_composition(process, State::new(r));
+    println!("{} states with the environment", lts.len());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn choose() {
+        let r: SharedVars = Default::default();
+        let lts = concurrent_composition(processes_def(), State::new(r));
+
+        /* one step per input of each process */
+        let first: Vec<&str> = lts
+            .trans()
+            .iter()
+            .filter(|t| t.before == 0)
+            .map(|t| t.label.as_str())
+            .collect();
+        assert_eq!(
+            first,
+            vec![
+                "P0.read(0)",
+                "P0.read(1)",
+                "P0.read(2)",
+                "P1.read(0)",
+                "P1.read(1)",
+                "P1.read(2)"
+            ]
+        );
+
+        /* every sum from 0 to 4, each with the inputs that make it */
+        let mut sums: Vec<i32> = lts
+            .detect_termination()
+            .iter()
+            .map(|d| lts.state(*d).unwrap().shared_vars.sum)
+            .collect();
+        sums.sort();
+        assert_eq!(sums, vec![0, 1, 1, 2, 2, 2, 3, 3, 4]);
+    }
+
+    #[test]
+    fn havoc() {
+        let r: SharedVars = Default::default();
+        let lts = concurrent_composition(vec![havoc_def()], State::new(r));
+        let labels: Vec<&str> = lts.trans().iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(labels, vec!["E.havoc(keep)"]);
+
+        let lts = concurrent_composition(vec![havoc_def()], State::new(SharedVars { sum: 3 }));
+        let labels: Vec<&str> = lts.trans().iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(labels, vec!["E.havoc(keep)", "E.havoc(clear)"]);
+        let done = lts.detect_termination();
+        assert_eq!(done.len(), 2);
+
+        /* together with the readers, the sum may end up cleared */
+        let mut process = processes_def();
+        process.push(havoc_def());
+        let lts = concurrent_composition(process, State::new(r));
+        assert!(lts.trans().iter().any(|t| t.label == "E.havoc(clear)"));
+    }
+}
@@ -0,0 +1,179 @@
+use rddsv::lts::*;
+use rddsv::process::*;
+use rddsv::search::*;
+use std::fmt;
+
+#[derive(Default, std::fmt::Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SharedVars {
+    pub x: i32, // counted up by P, never reset.
+    pub y: i32, // counted up by Q, never reset.
+}
+
+impl fmt::Display for SharedVars {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "x={} y={}", self.x, self.y)
+    }
+}
+
+/* User definition of guard and action */
+fn action_p_inc(_prop: Prop, a: &mut SharedVars, b: &SharedVars) {
+    a.x = b.x + 1;
+}
+
+fn action_q_inc(_prop: Prop, a: &mut SharedVars, b: &SharedVars) {
+    a.y = b.y + 1;
+}
+
+fn processes_def() -> Vec<Process<SharedVars>> {
+    /* Create Process "P" and "Q", each one counting forever */
+    let p_inc = ProcessTrans::new("inc", 0, guard_true, action_p_inc);
+    let q_inc = ProcessTrans::new("inc", 0, guard_true, action_q_inc);
+
+    let p = Process::new("P", vec![ExecUnit::new(0, vec![p_inc])]);
+    let q = Process::new("Q", vec![ExecUnit::new(0, vec![q_inc])]);
+    vec![p, q]
+}
+
+/* the same counters, each stopping at `limit` */
+fn bounded_def(limit: i32) -> Vec<Process<SharedVars>> {
+    let p_inc = ProcessTrans::new_closure(
+        "inc",
+        0,
+        move |_: &Ctx, v: &SharedVars| v.x < limit,
+        |_: &Ctx, v: &mut SharedVars| v.x += 1,
+    );
+    let q_inc = ProcessTrans::new_closure(
+        "inc",
+        0,
+        move |_: &Ctx, v: &SharedVars| v.y < limit,
+        |_: &Ctx, v: &mut SharedVars| v.y += 1,
+    );
+
+    let p = Process::new("P", vec![ExecUnit::new_end(0, vec![p_inc])]);
+    let q = Process::new("Q", vec![ExecUnit::new_end(0, vec![q_inc])]);
+    vec![p, q]
+}
+
+pub fn main() {
+    /* the state space is infinite, so explore it up to a bound */
+    let r: SharedVars = Default::default();
+    let s = State::new(r);
+    let config = SearchConfig::new().max_depth(3);
+    let lts = concurrent_composition_with(processes_def(), s, &config);
+    if let Some(p) = lts.partial() {
+        println!("partial: {:?}, unexplored {:?}", p.limits, p.frontier);
+    }
+    lts.visualize("res/m_counter.dot");
+
+    let lts = concurrent_composition(bounded_def(3), State::new(r));
+    println!("counting up to 3: {} states", lts.len());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rddsv::parallel::*;
+    use std::time::Duration;
+
+    fn explore(config: &SearchConfig<SharedVars>) -> Lts<SharedVars> {
+        let r: SharedVars = Default::default();
+        let s = State::new(r);
+        concurrent_composition_with(processes_def(), s, config)
+    }
+
+    #[test]
+    fn depth() {
+        /* 1 + 2 + 3 states within two steps */
+        let lts = explore(&SearchConfig::new().max_depth(2));
+        assert_eq!(lts.len(), 6);
+        let p = lts.partial().unwrap();
+        assert_eq!(p.limits, vec![Limit::Depth]);
+        assert_eq!(p.frontier, vec![3, 4, 5]);
+        assert!(lts.is_unexplored(5));
+        /* the frontier is not mistaken for deadlocks */
+        assert!(lts.deadlocks().is_empty());
+    }
+
+    #[test]
+    fn states() {
+        let lts = explore(&SearchConfig::new().max_states(10));
+        assert_eq!(lts.len(), 10);
+        let p = lts.partial().unwrap();
+        assert_eq!(p.limits, vec![Limit::States]);
+        assert!(!p.frontier.is_empty());
+        assert!(lts.deadlocks().is_empty());
+    }
+
+    #[test]
+    fn trans() {
+        let lts = explore(&SearchConfig::new().max_trans(7));
+        assert_eq!(lts.trans().len(), 7);
+        assert_eq!(lts.partial().unwrap().limits, vec![Limit::Trans]);
+    }
+
+    #[test]
+    fn time() {
+        let lts = explore(&SearchConfig::new().time_limit(Duration::from_secs(0)));
+        assert_eq!(lts.len(), 1);
+        let p = lts.partial().unwrap();
+        assert_eq!(p.limits, vec![Limit::Time]);
+        assert_eq!(p.frontier, vec![0]);
+    }
+
+    #[test]
+    fn dfs() {
+        /* depth first runs down one counter, the bound still holds */
+        let lts = explore(&SearchConfig::new().strategy(Strategy::Dfs).max_states(5));
+        assert_eq!(lts.len(), 5);
+        assert_eq!(lts.partial().unwrap().limits, vec![Limit::States]);
+    }
+
+    #[test]
+    fn parallel() {
+        let r: SharedVars = Default::default();
+        let explore = |config: SearchConfig<SharedVars>| {
+            let config = config.threads(4).canonical(true);
+            concurrent_composition_parallel(processes_def(), State::new(r), &config)
+        };
+
+        /* the same states and frontier as the sequential Bfs */
+        let lts = explore(SearchConfig::new().max_depth(2));
+        assert_eq!(lts.len(), 6);
+        let p = lts.partial().unwrap();
+        assert_eq!(p.limits, vec![Limit::Depth]);
+        assert_eq!(p.frontier, vec![3, 4, 5]);
+        assert!(lts.deadlocks().is_empty());
+
+        let lts = explore(SearchConfig::new().max_states(10));
+        assert_eq!(lts.len(), 10);
+        assert_eq!(lts.partial().unwrap().limits, vec![Limit::States]);
+        assert!(lts.deadlocks().is_empty());
+
+        let lts = explore(SearchConfig::new().max_trans(7));
+        assert_eq!(lts.trans().len(), 7);
+        assert_eq!(lts.partial().unwrap().limits, vec![Limit::Trans]);
+
+        let lts = explore(SearchConfig::new().time_limit(Duration::from_secs(0)));
+        assert_eq!(lts.len(), 1);
+        assert_eq!(lts.partial().unwrap().frontier, vec![0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "on the fly checks")]
+    fn parallel_checks() {
+        /* on the fly checks are left to concurrent_composition_with */
+        let r: SharedVars = Default::default();
+        let config = SearchConfig::new().threads(2).deadlock(true);
+        concurrent_composition_parallel(processes_def(), State::new(r), &config);
+    }
+
+    #[test]
+    fn closure() {
+        /* the guards capture the limit */
+        let r: SharedVars = Default::default();
+        let lts = concurrent_composition(bounded_def(3), State::new(r));
+        assert_eq!(lts.len(), 16);
+        assert!(!lts.is_partial());
+        assert_eq!(lts.detect_termination().len(), 1);
+    }
+}
@@ -0,0 +1,111 @@
+use rddsv::lts::*;
+use rddsv::process::*;
+use std::fmt;
+
+#[derive(Default, std::fmt::Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SharedVars {
+    pub coffees: i32, // drinks the machine served.
+    pub teas: i32,
+}
+
+impl fmt::Display for SharedVars {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "coffees={} teas={}", self.coffees, self.teas)
+    }
+}
+
+/* User definition of guard and action */
+fn guard_stock(_prop: Prop, c: SharedVars) -> bool {
+    c.coffees + c.teas < 2
+}
+
+fn action_coffee(_prop: Prop, a: &mut SharedVars, b: &SharedVars) {
+    a.coffees = b.coffees + 1;
+}
+
+fn action_tea(_prop: Prop, a: &mut SharedVars, b: &SharedVars) {
+    a.teas = b.teas + 1;
+}
+
+/* a vending machine taking a coin for either drink, while stocks last */
+fn machine_def() -> Process<SharedVars> {
+    let coin = ProcessTrans::new("coin", 1, guard_stock, action_nop);
+    let coffee = ProcessTrans::new("coffee", 0, guard_true, action_coffee);
+    let tea = ProcessTrans::new("tea", 0, guard_true, action_tea);
+
+    let m0 = ExecUnit::new_end(0, vec![coin]);
+    let m1 = ExecUnit::new(1, vec![coffee, tea]);
+    Process::new("VM", vec![m0, m1])
+}
+
+/* a customer paying once for a coffee */
+fn customer_def() -> Process<SharedVars> {
+    let coin = ProcessTrans::new("coin", 1, guard_true, action_nop);
+    let coffee = ProcessTrans::new("coffee", 2, guard_true, action_nop);
+
+    let c0 = ExecUnit::new(0, vec![coin]);
+    let c1 = ExecUnit::new(1, vec![coffee]);
+    let c2 = ExecUnit::new_end(2, vec![]);
+    Process::new("C", vec![c0, c1, c2])
+}
+
+/* the machine and the customer handshake on every label of the machine,
+ * tea taking a customer who never asks for it
+ */
+fn processes_def() -> Vec<Process<SharedVars>> {
+    let customer = customer_def().alphabet(&["coin", "coffee", "tea"]);
+    vec![machine_def().synchronize(), customer]
+}
+
+pub fn main() {
+    let r: SharedVars = Default::default();
+    let lts = concurrent_composition(processes_def(), State::new(r));
+    lts.visualize("res/m_csp.dot");
+
+    let free = concurrent_composition(vec![machine_def(), customer_def()], State::new(r));
+    println!(
+        "{} states synchronized, {} interleaved",
+        lts.len(),
+        free.len()
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn handshake() {
+        let r: SharedVars = Default::default();
+        let lts = concurrent_composition(processes_def(), State::new(r));
+
+        /* the customer never asks for tea, so the machine never serves it */
+        let labels: Vec<&str> = lts.trans().iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(labels, vec!["VM.coin|C.coin", "VM.coffee|C.coffee"]);
+        assert!(lts.trans().iter().all(|t| t.involves(0) && t.involves(1)));
+        let done = lts.detect_termination();
+        assert_eq!(done, vec![2]);
+        let v = lts.state(2).unwrap().shared_vars;
+        assert_eq!((v.coffees, v.teas), (1, 0));
+
+        /* interleaved, the machine serves on its own */
+        let lts = concurrent_composition(vec![machine_def(), customer_def()], State::new(r));
+        assert!(lts.trans().iter().any(|t| t.label == "VM.tea"));
+    }
+
+    #[test]
+    fn environment() {
+        /* a process with tea in its alphabet but no way to fire it rules
+         * tea out, while the coins of the machine and the customer still
+         * interleave
+         */
+        let r: SharedVars = Default::default();
+        let no_tea = Process::new("E", vec![ExecUnit::new_end(0, vec![])]).alphabet(&["tea"]);
+        let process = vec![machine_def().synchronize(), customer_def(), no_tea];
+        let lts = concurrent_composition(process, State::new(r));
+        assert!(lts.trans().iter().all(|t| !t.label.contains("tea")));
+        assert!(lts.trans().iter().any(|t| t.label == "VM.coin"));
+        assert!(lts.trans().iter().any(|t| t.label == "C.coin"));
+        assert!(lts.deadlocks().is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+use rddsv::liveness::*;
+use rddsv::lts::*;
+use rddsv::process::*;
+use std::fmt;
+
+#[derive(Default, std::fmt::Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SharedVars {
+    pub x: i32, // set by Q, P is blocked while it is 2.
+}
+
+impl fmt::Display for SharedVars {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "x={}", self.x)
+    }
+}
+
+fn guard_go(_prop: Prop, c: SharedVars) -> bool {
+    c.x != 2
+}
+
+/* P makes progress whenever x is not 2 */
+fn p_def() -> Process<SharedVars> {
+    let go = ProcessTrans::new_progress("go", 0, guard_go, action_nop);
+    Process::new("P", vec![ExecUnit::new(0, vec![go])])
+}
+
+/* Q sets x to 1 or 2 and puts it back */
+fn q_def() -> Process<SharedVars> {
+    let set = |name: &str, x: i32| {
+        ProcessTrans::new_closure(
+            name,
+            1,
+            |_: &Ctx, _: &SharedVars| true,
+            move |_: &Ctx, v: &mut SharedVars| v.x = x,
+        )
+    };
+    let back = ProcessTrans::new_closure(
+        "back",
+        0,
+        |_: &Ctx, _: &SharedVars| true,
+        |_: &Ctx, v: &mut SharedVars| v.x = 0,
+    );
+
+    let q0 = ExecUnit::new(0, vec![set("a", 1), set("b", 2)]);
+    let q1 = ExecUnit::new(1, vec![back]);
+    Process::new("Q", vec![q0, q1])
+}
+
+fn processes_def() -> Vec<Process<SharedVars>> {
+    vec![p_def(), q_def()]
+}
+
+pub fn main() {
+    let r: SharedVars = Default::default();
+    let lts = concurrent_composition(processes_def(), State::new(r));
+    lts.visualize("res/m_fair.dot");
+
+    let wf = Fairness::new().weak("P");
+    for l in lts.detect_livelock_fair(&wf).unwrap() {
+        println!("livelock: {}", l);
+    }
+    for l in lts.detect_starvation("P", &[], &wf).unwrap() {
+        println!("P starves: {}", l);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /* under weak fairness P fires or is disabled somewhere on the loop */
+    fn fair_to_p(lts: &Lts<SharedVars>, l: &Lasso) -> bool {
+        let enabled = |s: StateId| lts.trans().iter().any(|t| t.before == s && t.involves(0));
+        l.cycle.iter().any(|t| t.involves(0) || !enabled(t.before))
+    }
+
+    #[test]
+    fn livelock() {
+        let r: SharedVars = Default::default();
+        let lts = concurrent_composition(processes_def(), State::new(r));
+
+        /* only Q loops without progress. P is enabled along Q.a Q.back, so
+         * a fair loop has to pass x=2
+         */
+        let l = lts
+            .detect_livelock_fair(&Fairness::new().weak("P"))
+            .unwrap();
+        assert_eq!(l.len(), 1);
+        assert!(fair_to_p(&lts, &l[0]));
+        let labels: Vec<&str> = l[0].cycle.iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(labels, vec!["Q.b", "Q.back"]);
+
+        let l = lts.detect_livelock();
+        let labels: Vec<&str> = l[0].cycle.iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(labels, vec!["Q.a", "Q.back"]);
+    }
+
+    #[test]
+    fn starvation() {
+        let r: SharedVars = Default::default();
+        let lts = concurrent_composition(processes_def(), State::new(r));
+
+        /* P starves only by Q passing x=2 every round */
+        let l = lts
+            .detect_starvation("P", &[], &Fairness::new().weak("P"))
+            .unwrap();
+        assert_eq!(l.len(), 1);
+        assert!(fair_to_p(&lts, &l[0]));
+        let labels: Vec<&str> = l[0].cycle.iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(labels, vec!["Q.b", "Q.back"]);
+
+        /* with no fairness Q.a Q.back starves it as well */
+        let l = lts.detect_starvation("P", &[], &Fairness::new()).unwrap();
+        let labels: Vec<&str> = l[0].cycle.iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(labels, vec!["Q.a", "Q.back"]);
+    }
+}
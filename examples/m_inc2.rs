@@ -1,5 +1,8 @@
+use rddsv::formula::*;
+use rddsv::ltl::*;
 use rddsv::lts::*;
 use rddsv::process::*;
+use rddsv::search::*;
 use std::fmt;
 
 #[derive(Default, std::fmt::Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -33,17 +36,14 @@ fn m_inc2_p_def() -> Process<SharedVars> {
     let p_read = ProcessTrans::new("read", 1, guard_true, action_p_read);
     let p_inc = ProcessTrans::new("inc", 2, guard_true, action_p_inc);
     let p_write = ProcessTrans::new("write", 3, guard_true, action_p_write);
-    /*TODO: Add stop state */
 
     let p0 = ExecUnit::new(0, vec![p_read]);
     let p1 = ExecUnit::new(1, vec![p_inc]);
     let p2 = ExecUnit::new(2, vec![p_write]);
-    let p3 = ExecUnit::new(3, vec![]);
+    let p3 = ExecUnit::new_end(3, vec![]);
 
     let p_trans = vec![p0, p1, p2, p3];
-    let p = Process::new("P", p_trans);
-
-    return p;
+    Process::new("P", p_trans)
 }
 
 fn action_q_read(_prop: Prop, a: &mut SharedVars, b: &SharedVars) {
@@ -67,12 +67,25 @@ fn m_inc2_q_def() -> Process<SharedVars> {
     let q0 = ExecUnit::new(0, vec![q_read]);
     let q1 = ExecUnit::new(1, vec![q_inc]);
     let q2 = ExecUnit::new(2, vec![q_write]);
-    let q3 = ExecUnit::new(3, vec![]);
+    let q3 = ExecUnit::new_end(3, vec![]);
 
     let q_trans = vec![q0, q1, q2, q3];
-    let q = Process::new("Q", q_trans);
+    Process::new("Q", q_trans)
+}
 
-    return q;
+/* both processes have written back, so x must have been incremented twice */
+fn inv_inc2(s: &State<SharedVars>) -> bool {
+    let done = Location::new(3);
+    !(s.locations[0] == done && s.locations[1] == done) || s.shared_vars.x == 2
+}
+
+/* nobody wrote x between P reading it and writing it back */
+fn assert_fresh(_prop: Prop, c: SharedVars) -> bool {
+    c.x == c.t1 - 1
+}
+
+fn pred_two(c: &SharedVars) -> bool {
+    c.x == 2
 }
 
 fn m_inc2_def() -> Vec<Process<SharedVars>> {
@@ -93,6 +106,24 @@ pub fn main() {
     let s = State::new(r);
     let lts = concurrent_composition(process, s);
     lts.visualize("res/m_inc2.dot");
+    for v in lts.check_invariant(inv_inc2) {
+        println!("invariant violated at {}", v);
+    }
+
+    let preds: Vec<(&str, Predicate<SharedVars>)> = vec![("two", pred_two)];
+    let ltl = Ltl::parse("F G two").unwrap();
+    if let Some(l) = lts.check_ltl(&ltl, &preds).unwrap() {
+        println!("{} violated by {}", ltl, l);
+    }
+
+    /* the same race, found while exploring */
+    let mut process = m_inc2_def();
+    process[0].v[2] = process[0].v[2].clone().assert(assert_fresh);
+    let config = SearchConfig::new().stop_on_error(true);
+    let lts = concurrent_composition_with(process, State::new(r), &config);
+    for (fault, v) in lts.faults() {
+        println!("{} at {}", fault, v);
+    }
 }
 
 #[cfg(test)]
@@ -145,4 +176,76 @@ mod test {
             println!("! {:?}", why.kind());
         });
     }
+
+    #[test]
+    fn invariant() {
+        let process = m_inc2_def();
+        let r: SharedVars = Default::default();
+        let s = State::new(r);
+        let lts = concurrent_composition(process, s);
+        let v = lts.check_invariant(inv_inc2);
+
+        assert_eq!(v.len(), 1);
+        assert_eq!(lts.state(v[0].state).unwrap().shared_vars.x, 1);
+        let labels: Vec<&str> = v[0].path.iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            vec!["P.read", "P.inc", "Q.read", "P.write", "Q.inc", "Q.write"]
+        );
+    }
+
+    #[test]
+    fn ltl() {
+        let process = m_inc2_def();
+        let r: SharedVars = Default::default();
+        let s = State::new(r);
+        let lts = concurrent_composition(process, s);
+        let preds: Vec<(&str, Predicate<SharedVars>)> = vec![("two", pred_two)];
+
+        let done = Ltl::parse("F (P@3 && Q@3)").unwrap();
+        assert!(lts.check_ltl(&done, &preds).unwrap().is_none());
+
+        let two = Ltl::parse("F G two").unwrap();
+        let l = lts.check_ltl(&two, &preds).unwrap().unwrap();
+        assert_eq!(l.prefix.len(), 6);
+        assert_eq!(lts.state(l.entry()).unwrap().shared_vars.x, 1);
+        assert_eq!(l.cycle[0].label, "stutter");
+    }
+
+    #[test]
+    fn on_the_fly() {
+        let r: SharedVars = Default::default();
+        let config = SearchConfig::new().invariant("inc2", inv_inc2);
+        let lts = concurrent_composition_with(m_inc2_def(), State::new(r), &config);
+        assert!(!lts.is_partial());
+        assert_eq!(lts.faults().len(), 1);
+
+        let config = config.stop_on_error(true);
+        let lts = concurrent_composition_with(m_inc2_def(), State::new(r), &config);
+        assert_eq!(lts.partial().unwrap().limits, vec![Limit::Error]);
+        let (fault, v) = &lts.faults()[0];
+        assert_eq!(*fault, Fault::Invariant("inc2".to_string()));
+        let labels: Vec<&str> = v.path.iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            vec!["P.read", "P.inc", "Q.read", "P.write", "Q.inc", "Q.write"]
+        );
+        assert!(lts.len() < 22);
+    }
+
+    #[test]
+    fn assertion() {
+        let mut process = m_inc2_def();
+        process[0].v[2] = process[0].v[2].clone().assert(assert_fresh);
+        let r: SharedVars = Default::default();
+        let config = SearchConfig::new().stop_on_error(true);
+        let lts = concurrent_composition_with(process, State::new(r), &config);
+
+        assert_eq!(lts.faults().len(), 1);
+        let (fault, v) = &lts.faults()[0];
+        assert_eq!(*fault, Fault::Assertion(Label::new("P"), Location::new(2)));
+        assert_eq!(lts.state(v.state).unwrap().shared_vars.x, 1);
+        assert_eq!(v.path.len(), 5);
+        assert!(v.path.iter().any(|t| t.label == "Q.write"));
+    }
 }
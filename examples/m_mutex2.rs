@@ -1,11 +1,15 @@
+use rddsv::codec::*;
+use rddsv::ctl::*;
 use rddsv::lts::*;
 use rddsv::process::*;
+use rddsv::search::*;
+use rddsv::store::*;
 use std::fmt;
 
 #[derive(Default, std::fmt::Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct SharedVars {
-    pub m0: i32,  // mutex0
-    pub m1: i32,  // mutex1
+    pub m0: i32, // mutex0
+    pub m1: i32, // mutex1
 }
 
 impl fmt::Display for SharedVars {
@@ -14,6 +18,20 @@ impl fmt::Display for SharedVars {
     }
 }
 
+impl Codec for SharedVars {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.m0.encode(out);
+        self.m1.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(SharedVars {
+            m0: i32::decode(input)?,
+            m1: i32::decode(input)?,
+        })
+    }
+}
+
 /* User definition of guard and action
  * a = after, b = before, c = current
  */
@@ -54,9 +72,7 @@ fn p_def() -> Process<SharedVars> {
     let p3 = ExecUnit::new(3, vec![p_unlock0]);
 
     let p = vec![p0, p1, p2, p3];
-    let ret = Process::new("P", p);
-
-    ret
+    Process::new("P", p)
 }
 
 fn q_def() -> Process<SharedVars> {
@@ -72,9 +88,7 @@ fn q_def() -> Process<SharedVars> {
     let p3 = ExecUnit::new(3, vec![p_unlock1]);
 
     let p = vec![p0, p1, p2, p3];
-    let ret = Process::new("Q", p);
-
-    ret
+    Process::new("Q", p)
 }
 
 fn processes_def() -> Vec<Process<SharedVars>> {
@@ -92,14 +106,36 @@ pub fn main() {
 
     let r: SharedVars = Default::default();
     let s = State::new(r);
-    let lts = concurrent_composition(process, s);
+    let mut lts = concurrent_composition(process, s);
     lts.visualize("res/m_lock.dot");
+    for d in lts.deadlocks() {
+        println!("deadlock at {}", d);
+    }
+
+    /* the system can always return home */
+    let home = Ctl::parse("AG EF (P@0 && Q@0)").unwrap();
+    let sat = lts.check_ctl(&home, &[]).unwrap();
+    println!("{} holds in {:?}", home, sat);
+    lts.mark_ctl(&sat);
+    lts.visualize("res/m_lock_ctl.dot");
+
+    /* a supertrace run remembers a bit table only */
+    let config = SearchConfig::new().deadlock(true).store(Store::Bitstate {
+        log2_bits: 16,
+        hashes: 2,
+    });
+    let c = coverage(processes_def(), State::new(r), &config);
+    println!("{}", c);
+    for f in &c.faults {
+        println!("{}", f);
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
     use file_diff::diff_files;
+    use rddsv::disk::*;
     use std::fs::*;
 
     #[test]
@@ -146,4 +182,134 @@ mod test {
             println!("! {:?}", why.kind());
         });
     }
+
+    #[test]
+    fn deadlock() {
+        let process = processes_def();
+        let r: SharedVars = Default::default();
+        let s = State::new(r);
+        let lts = concurrent_composition(process, s);
+        let d = lts.deadlocks();
+
+        assert_eq!(d.len(), 1);
+        assert_eq!(d[0].state, 4);
+        let labels: Vec<&str> = d[0].path.iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(labels, vec!["P.lock0", "Q.lock1"]);
+    }
+
+    #[test]
+    fn ctl() {
+        let process = processes_def();
+        let r: SharedVars = Default::default();
+        let s = State::new(r);
+        let lts = concurrent_composition(process, s);
+
+        let home = Ctl::parse("AG EF (P@0 && Q@0)").unwrap();
+        assert!(lts.check_ctl(&home, &[]).unwrap().is_empty());
+
+        let reach = Ctl::parse("EF (P@0 && Q@0)").unwrap();
+        let sat = lts.check_ctl(&reach, &[]).unwrap();
+        assert!(sat.contains(&0));
+        assert!(!sat.contains(&4));
+
+        let sub = lts.ctl_subformulas(&home, &[]).unwrap();
+        assert_eq!(sub.len(), 5);
+        assert_eq!(sub[3].1, sat);
+    }
+
+    /* both locks taken is where the deadlock lives */
+    fn locks_held(s: &State<SharedVars>) -> i64 {
+        -(s.shared_vars.m0 + s.shared_vars.m1) as i64
+    }
+
+    #[test]
+    fn strategies() {
+        let strategies = vec![
+            Strategy::Bfs,
+            Strategy::Dfs,
+            Strategy::RandomDfs(7),
+            Strategy::BestFirst(locks_held),
+        ];
+        for st in strategies {
+            let process = processes_def();
+            let r: SharedVars = Default::default();
+            let s = State::new(r);
+            /* bounds the model never reaches leave the result complete */
+            let config = SearchConfig::new()
+                .strategy(st)
+                .max_states(10)
+                .max_depth(10);
+            let lts = concurrent_composition_with(process, s, &config);
+
+            assert!(!lts.is_partial());
+            assert_eq!(lts.len(), 10);
+            assert_eq!(lts.trans().len(), 14);
+            let d = lts.deadlocks();
+            assert_eq!(d.len(), 1);
+            let dead = lts.state(d[0].state).unwrap();
+            assert_eq!(dead.locations, vec![Location::new(1), Location::new(1)]);
+        }
+    }
+
+    #[test]
+    fn on_the_fly() {
+        let r: SharedVars = Default::default();
+        let config = SearchConfig::new().deadlock(true).stop_on_error(true);
+        let lts = concurrent_composition_with(processes_def(), State::new(r), &config);
+
+        assert_eq!(lts.faults().len(), 1);
+        let (fault, v) = &lts.faults()[0];
+        assert_eq!(*fault, Fault::Deadlock);
+        let labels: Vec<&str> = v.path.iter().map(|t| t.label.as_str()).collect();
+        assert_eq!(labels, vec!["P.lock0", "Q.lock1"]);
+        assert_eq!(lts.partial().unwrap().limits, vec![Limit::Error]);
+        /* states left on the frontier are not reported as deadlocks */
+        assert_eq!(lts.deadlocks().len(), 1);
+    }
+
+    #[test]
+    fn bitstate() {
+        let r: SharedVars = Default::default();
+        let config = SearchConfig::new()
+            .deadlock(true)
+            .stop_on_error(true)
+            .store(Store::Bitstate {
+                log2_bits: 16,
+                hashes: 2,
+            });
+        let c = coverage(processes_def(), State::new(r), &config);
+
+        assert_eq!(c.faults.len(), 1);
+        let f = &c.faults[0];
+        assert_eq!(f.fault, Fault::Deadlock);
+        assert_eq!(f.state.locations, vec![Location::new(1), Location::new(1)]);
+        /* depth first, so not the shortest way there */
+        assert!(f.path.len() >= 2);
+    }
+
+    #[test]
+    fn disk() {
+        let r: SharedVars = Default::default();
+        let lts = concurrent_composition(processes_def(), State::new(r));
+        let expected: Vec<String> = lts.deadlocks()[0]
+            .path
+            .iter()
+            .map(|t| t.label.clone())
+            .collect();
+
+        /* a budget of a few states forces every level through the files */
+        let dir = std::env::temp_dir().join(format!("rddsv_m_lock_disk_{}", std::process::id()));
+        let config = SearchConfig::new().deadlock(true);
+        for memory in &[200, 1 << 20] {
+            let c =
+                coverage_on_disk(processes_def(), State::new(r), &config, &dir, *memory).unwrap();
+            assert_eq!((c.states, c.trans), (lts.len(), lts.trans().len()));
+            assert_eq!(c.faults.len(), 1);
+            assert_eq!(c.faults[0].fault, Fault::Deadlock);
+            assert_eq!(c.faults[0].path, expected);
+        }
+        std::fs::remove_dir(&dir).unwrap_or_else(|why| {
+            println!("! {:?}", why.kind());
+        });
+    }
 }
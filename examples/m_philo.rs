@@ -0,0 +1,111 @@
+use rddsv::lts::*;
+use rddsv::process::*;
+use std::fmt;
+
+#[derive(Default, std::fmt::Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SharedVars {
+    pub forks: u32, // bit i is set while fork i is taken.
+}
+
+impl fmt::Display for SharedVars {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "forks={:b}", self.forks)
+    }
+}
+
+fn take(fork: u32, dst: usize) -> ProcessTrans<SharedVars> {
+    ProcessTrans::new_closure(
+        &format!("take{}", fork),
+        dst,
+        move |_: &Ctx, v: &SharedVars| v.forks & (1 << fork) == 0,
+        move |_: &Ctx, v: &mut SharedVars| v.forks |= 1 << fork,
+    )
+}
+
+/* philosopher `prop` of `n` sits between fork prop and its right
+ * neighbour's. with `asymmetric` the last one reaches for the right fork
+ * first, which breaks the circular wait.
+ */
+fn philo_def(n: usize, asymmetric: bool) -> impl Fn(Prop) -> Vec<ExecUnit<SharedVars>> {
+    move |prop: Prop| {
+        let left = prop as u32;
+        let right = (prop as u32 + 1) % n as u32;
+        let (first, second) = if asymmetric && prop as usize == n - 1 {
+            (right, left)
+        } else {
+            (left, right)
+        };
+        let take_first = take(first, 1);
+        let take_second = take(second, 2);
+        let release = ProcessTrans::new_closure(
+            "release",
+            0,
+            |_: &Ctx, _: &SharedVars| true,
+            move |_: &Ctx, v: &mut SharedVars| v.forks &= !(1 << left | 1 << right),
+        );
+
+        let p0 = ExecUnit::new(0, vec![take_first]);
+        let p1 = ExecUnit::new(1, vec![take_second]);
+        let p2 = ExecUnit::new_progress(2, vec![release]);
+        vec![p0, p1, p2]
+    }
+}
+
+fn processes_def(n: usize, asymmetric: bool) -> Vec<Process<SharedVars>> {
+    Process::instances("Phil", n, philo_def(n, asymmetric))
+}
+
+pub fn main() {
+    /* visualize each process */
+    let process = processes_def(3, false);
+    process[2].visualize("res/m_philo_Phil2.dot");
+
+    let r: SharedVars = Default::default();
+    let lts = concurrent_composition(process, State::new(r));
+    lts.visualize("res/m_philo.dot");
+    for v in lts.deadlocks() {
+        println!("deadlock at {}", v);
+    }
+
+    let fixed = concurrent_composition(processes_def(3, true), State::new(r));
+    println!(
+        "{} deadlocks once Phil2 is left-handed",
+        fixed.deadlocks().len()
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn instances() {
+        let process = processes_def(4, false);
+        let labels: Vec<String> = process.iter().map(|p| p.label.to_string()).collect();
+        assert_eq!(labels, vec!["Phil0", "Phil1", "Phil2", "Phil3"]);
+        assert!(process.iter().enumerate().all(|(i, p)| p.prop == i as Prop));
+
+        /* the last one shares fork 0 with the first */
+        assert_eq!(process[3].v[0].transs[0].label.to_string(), "take3");
+        assert_eq!(process[3].v[1].transs[0].label.to_string(), "take0");
+    }
+
+    #[test]
+    fn deadlock() {
+        let r: SharedVars = Default::default();
+        for n in 2..5 {
+            /* everyone holding the left fork */
+            let lts = concurrent_composition(processes_def(n, false), State::new(r));
+            let d = lts.deadlocks();
+            assert_eq!(d.len(), 1);
+            assert_eq!(d[0].path.len(), n);
+            assert_eq!(
+                lts.state(d[0].state).unwrap().shared_vars.forks,
+                (1 << n) - 1
+            );
+
+            let lts = concurrent_composition(processes_def(n, true), State::new(r));
+            assert!(lts.deadlocks().is_empty());
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use rddsv::lts::*;
+use rddsv::process::*;
+use rddsv::search::*;
+use std::fmt;
+
+#[derive(Default, std::fmt::Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SharedVars {
+    pub sum: i32, // shared among P, Q and R.
+    pub a: i32,   // P's local variables.
+    pub b: i32,   // Q's local variables.
+    pub c: i32,   // R's local variables.
+}
+
+impl fmt::Display for SharedVars {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "sum={} a={} b={} c={}",
+            self.sum, self.a, self.b, self.c
+        )
+    }
+}
+
+/* the local variable of the worker numbered `prop` */
+const LOCALS: [&str; 3] = ["a", "b", "c"];
+
+fn local(prop: Prop, v: &mut SharedVars) -> &mut i32 {
+    match prop {
+        0 => &mut v.a,
+        1 => &mut v.b,
+        _ => &mut v.c,
+    }
+}
+
+fn get_local(prop: Prop, v: &SharedVars) -> i32 {
+    match prop {
+        0 => v.a,
+        1 => v.b,
+        _ => v.c,
+    }
+}
+
+/* User definition of guard and action */
+fn action_load(prop: Prop, a: &mut SharedVars, _b: &SharedVars) {
+    *local(prop, a) = prop + 1;
+}
+
+fn action_double(prop: Prop, a: &mut SharedVars, b: &SharedVars) {
+    *local(prop, a) = get_local(prop, b) * 2;
+}
+
+fn action_add(prop: Prop, a: &mut SharedVars, b: &SharedVars) {
+    a.sum = b.sum + get_local(prop, b);
+}
+
+fn worker_def(label: &str, prop: Prop) -> Process<SharedVars> {
+    /* Create a worker computing its share locally before adding it up */
+    let l = LOCALS[prop as usize];
+    let load = ProcessTrans::new("load", 1, guard_true, action_load).access(&[], &[l]);
+    let double = ProcessTrans::new("double", 2, guard_true, action_double).access(&[l], &[l]);
+    let add = ProcessTrans::new("add", 3, guard_true, action_add).access(&[l, "sum"], &["sum"]);
+
+    let p0 = ExecUnit::new(0, vec![load]);
+    let p1 = ExecUnit::new(1, vec![double]);
+    let p2 = ExecUnit::new(2, vec![add]);
+    let p3 = ExecUnit::new_end(3, vec![]);
+
+    Process::new(label, vec![p0, p1, p2, p3]).with_prop(prop)
+}
+
+/* P and Q have both loaded but not doubled, which nothing rules out */
+fn inv_apart(s: &State<SharedVars>) -> bool {
+    !(s.shared_vars.a == 1 && s.shared_vars.b == 2)
+}
+
+fn processes_def() -> Vec<Process<SharedVars>> {
+    vec![worker_def("P", 0), worker_def("Q", 1), worker_def("R", 2)]
+}
+
+pub fn main() {
+    let r: SharedVars = Default::default();
+    let full = concurrent_composition(processes_def(), State::new(r));
+    let config = SearchConfig::new().reduction(true);
+    let lts = concurrent_composition_with(processes_def(), State::new(r), &config);
+    println!(
+        "{} states, {} with partial order reduction",
+        full.len(),
+        lts.len()
+    );
+    lts.visualize("res/m_sum3.dot");
+
+    let config = config.visible(&["a", "b"]).invariant("apart", inv_apart);
+    let lts = concurrent_composition_with(processes_def(), State::new(r), &config);
+    for (_, v) in lts.faults() {
+        println!("apart violated at {}", v);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reduction() {
+        let r: SharedVars = Default::default();
+        let full = concurrent_composition(processes_def(), State::new(r));
+        let config = SearchConfig::new().reduction(true);
+        let lts = concurrent_composition_with(processes_def(), State::new(r), &config);
+
+        /* only the order of the three adds is left */
+        assert_eq!(full.len(), 64);
+        assert_eq!(lts.len(), 14);
+        assert!(lts.deadlocks().is_empty());
+        let done = lts.detect_termination();
+        assert_eq!(done.len(), 1);
+        assert_eq!(lts.state(done[0]).unwrap().shared_vars.sum, 12);
+    }
+
+    #[test]
+    fn visible() {
+        /* the locations of P are observed, so P is never expanded alone */
+        let r: SharedVars = Default::default();
+        let config = SearchConfig::new().reduction(true).visible(&["P"]);
+        let lts = concurrent_composition_with(processes_def(), State::new(r), &config);
+        assert!(lts.len() > 14 && lts.len() < 64);
+
+        /* so is a worker writing an observed variable */
+        let config = SearchConfig::new().reduction(true).visible(&["a"]);
+        let lts = concurrent_composition_with(processes_def(), State::new(r), &config);
+        assert!(lts.len() > 14);
+    }
+
+    #[test]
+    fn invariant() {
+        /* the invariant reads a and b, so they must be visible for the
+         * state where P and Q have both loaded to be built
+         */
+        let r: SharedVars = Default::default();
+        let config = SearchConfig::new()
+            .reduction(true)
+            .visible(&["a", "b"])
+            .invariant("apart", inv_apart);
+        let lts = concurrent_composition_with(processes_def(), State::new(r), &config);
+        assert!(!lts.faults().is_empty());
+
+        let full =
+            concurrent_composition_with(processes_def(), State::new(r), &config.reduction(false));
+        assert!(!full.faults().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "must list their variables in visible")]
+    fn invariant_unobserved() {
+        let r: SharedVars = Default::default();
+        let config = SearchConfig::new()
+            .reduction(true)
+            .invariant("apart", inv_apart);
+        concurrent_composition_with(processes_def(), State::new(r), &config);
+    }
+}
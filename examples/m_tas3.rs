@@ -0,0 +1,160 @@
+use rddsv::lts::*;
+use rddsv::process::*;
+use rddsv::search::*;
+use std::fmt;
+
+#[derive(Default, std::fmt::Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SharedVars {
+    pub x: i32, // lock shared among P, Q and R.
+}
+
+impl fmt::Display for SharedVars {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "x={}", self.x)
+    }
+}
+
+/* User definition of guard and action
+ * a = after, b = before, c = current
+ */
+fn guard_tas(_prop: Prop, c: SharedVars) -> bool {
+    c.x == 0
+}
+
+fn action_tas(_prop: Prop, a: &mut SharedVars, _b: &SharedVars) {
+    a.x = 1;
+}
+
+fn action_unlock(_prop: Prop, a: &mut SharedVars, _b: &SharedVars) {
+    a.x = 0;
+}
+
+fn tas_def(label: &str) -> Process<SharedVars> {
+    /* Create a test-and-set worker */
+    let tas = ProcessTrans::new("TAS", 1, guard_tas, action_tas);
+    let unlock = ProcessTrans::new("unlock", 0, guard_true, action_unlock);
+
+    let p0 = ExecUnit::new(0, vec![tas]);
+    let p1 = ExecUnit::new(1, vec![unlock]);
+
+    let p = vec![p0, p1];
+    Process::new(label, p)
+}
+
+/* the workers have no fields of their own */
+fn no_locals(v: &SharedVars, _perm: &[usize]) -> SharedVars {
+    *v
+}
+
+fn processes_def() -> Vec<Process<SharedVars>> {
+    vec![tas_def("P"), tas_def("Q"), tas_def("R")]
+}
+
+pub fn main() {
+    /* visualize each process */
+    let process = processes_def();
+    process[0].visualize("res/m_tas3_P.dot");
+
+    let r: SharedVars = Default::default();
+    let s = State::new(r);
+    let lts = concurrent_composition(process, s);
+    lts.visualize("res/m_tas3.dot");
+
+    let config = SearchConfig::new().symmetric(&["P", "Q", "R"], no_locals);
+    let sym = concurrent_composition_with(processes_def(), State::new(r), &config);
+    sym.visualize("res/m_tas3_sym.dot");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use file_diff::diff_files;
+    use rddsv::liveness::*;
+    use std::fs::*;
+
+    #[test]
+    fn vis_lts() {
+        let process = processes_def();
+        let r: SharedVars = Default::default();
+        let s = State::new(r);
+        let lts = concurrent_composition(process, s);
+        lts.visualize("res/test_m_tas3.dot");
+
+        let mut file1 = match File::open("./res/test_m_tas3.dot") {
+            Ok(f) => f,
+            Err(e) => panic!("{}", e),
+        };
+        let mut file2 = match File::open("./ref/m_tas3.dot") {
+            Ok(f) => f,
+            Err(e) => panic!("{}", e),
+        };
+
+        assert!(diff_files(&mut file1, &mut file2), "They are different.");
+
+        std::fs::remove_file("res/test_m_tas3.dot").unwrap_or_else(|why| {
+            println!("! {:?}", why.kind());
+        });
+    }
+
+    #[test]
+    fn starvation() {
+        let process = processes_def();
+        let r: SharedVars = Default::default();
+        let s = State::new(r);
+        let lts = concurrent_composition(process, s);
+
+        /* R is enabled only when P and Q hand the lock over, so weak
+         * fairness does not save it, strong fairness on its TAS does
+         */
+        let wf = Fairness::new().weak("P").weak("Q").weak("R");
+        let l = lts.detect_starvation("R", &[0], &wf).unwrap();
+        assert!(!l.is_empty());
+        assert!(l[0].cycle.iter().all(|t| !t.label.starts_with("R.")));
+        let sf = wf.strong("R.TAS");
+        assert!(lts.detect_starvation("R", &[0], &sf).unwrap().is_empty());
+
+        let all = lts.detect_starvation_all(&Fairness::new()).unwrap();
+        assert_eq!(all.len(), 3);
+        assert!(all.iter().all(|(_, l)| !l.is_empty()));
+    }
+
+    #[test]
+    fn symmetry() {
+        let r: SharedVars = Default::default();
+        let config = SearchConfig::new().symmetric(&["P", "Q", "R"], no_locals);
+        let lts = concurrent_composition_with(processes_def(), State::new(r), &config);
+
+        /* the lock is free, or one of the three holds it */
+        assert_eq!(lts.len(), 2);
+        assert!(lts.deadlocks().is_empty());
+
+        /* every step is one of the full run, up to renaming P, Q and R in
+         * the state it reaches
+         */
+        let full = concurrent_composition(processes_def(), State::new(r));
+        let orbit = |s: &State<SharedVars>| {
+            let mut locs: Vec<usize> = s.locations.iter().map(|l| l.to_usize()).collect();
+            locs.sort_unstable();
+            (locs, s.shared_vars)
+        };
+        for t in lts.trans() {
+            let (before, after) = (lts.state(t.before).unwrap(), lts.state(t.after).unwrap());
+            assert!(full.trans().iter().any(|u| {
+                u.label == t.label
+                    && full.state(u.before).unwrap() == before
+                    && orbit(full.state(u.after).unwrap()) == orbit(after)
+            }));
+        }
+        let p = lts.trans().iter().find(|t| t.label == "P.TAS").unwrap();
+        assert_eq!(lts.state(p.after).unwrap().locations[0].to_usize(), 0);
+    }
+    #[test]
+    #[should_panic(expected = "not identical")]
+    fn symmetry_props() {
+        /* the guards of instances may tell them apart by their prop */
+        let r: SharedVars = Default::default();
+        let process = vec![tas_def("P"), tas_def("Q").with_prop(1), tas_def("R")];
+        let config = SearchConfig::new().symmetric(&["P", "Q", "R"], no_locals);
+        concurrent_composition_with(process, State::new(r), &config);
+    }
+}
@@ -45,12 +45,10 @@ fn p_def() -> Process<SharedVars> {
     let p1 = ExecUnit::new(1, vec![y1]);
     let p2 = ExecUnit::new(2, vec![z1]);
     let p3 = ExecUnit::new(3, vec![y0]);
-    let p4 = ExecUnit::new(4, vec![]);
+    let p4 = ExecUnit::new_end(4, vec![]);
 
     let p = vec![p0, p1, p2, p3, p4];
-    let ret = Process::new("P", p);
-
-    ret
+    Process::new("P", p)
 }
 
 fn processes_def() -> Vec<Process<SharedVars>> {
@@ -91,4 +89,15 @@ mod test {
         let lts = concurrent_composition(process, s);
         assert!(true);
     }
+
+    #[test]
+    fn termination() {
+        let process = processes_def();
+        let r: SharedVars = Default::default();
+        let s = State::new(r);
+        let lts = concurrent_composition(process, s);
+
+        assert!(lts.deadlocks().is_empty());
+        assert_eq!(lts.detect_termination(), vec![4]);
+    }
 }
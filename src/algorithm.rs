@@ -1,23 +1,114 @@
 pub struct AdjacencyList {
-    dimension: usize,
     list: Vec<Vec<usize>>,
 }
 
+impl Default for AdjacencyList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl AdjacencyList {
     pub fn new() -> AdjacencyList {
+        AdjacencyList { list: vec![vec![]] }
+    }
+
+    pub fn with_dimension(n: usize) -> AdjacencyList {
         AdjacencyList {
-            dimension: 0,
-            list: vec![vec![]],
+            list: vec![vec![]; n],
         }
     }
 
-    pub fn has_edge(edge: usize) -> bool {
-        true
+    pub fn len(&self) -> usize {
+        self.list.len()
     }
 
-    pub fn insert(&mut self, edge: usize, new: usize) {
-        match self.list.get_mut(edge) {
-            _ => {}
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn successors(&self, v: usize) -> &[usize] {
+        &self.list[v]
+    }
+
+    pub fn has_edge(&self, from: usize, to: usize) -> bool {
+        match self.list.get(from) {
+            Some(v) => v.contains(&to),
+            None => false,
         }
     }
+
+    pub fn insert(&mut self, from: usize, to: usize) {
+        let dimension = from.max(to) + 1;
+        if self.list.len() < dimension {
+            self.list.resize(dimension, vec![]);
+        }
+        self.list[from].push(to);
+    }
+
+    /* strongly connected components by Tarjan's algorithm, without recursion.
+     * components come out in reverse topological order.
+     */
+    pub fn scc(&self) -> Vec<Vec<usize>> {
+        let n = self.list.len();
+        let mut index = vec![usize::MAX; n];
+        let mut low = vec![0; n];
+        let mut on_stack = vec![false; n];
+        let mut stack = Vec::new();
+        let mut ret = Vec::new();
+        let mut next = 0;
+
+        for root in 0..n {
+            if index[root] != usize::MAX {
+                continue;
+            }
+            index[root] = next;
+            low[root] = next;
+            next += 1;
+            stack.push(root);
+            on_stack[root] = true;
+            let mut call: Vec<(usize, usize)> = vec![(root, 0)];
+
+            while let Some(top) = call.last_mut() {
+                let v = top.0;
+                if top.1 < self.list[v].len() {
+                    let w = self.list[v][top.1];
+                    top.1 += 1;
+                    if index[w] == usize::MAX {
+                        index[w] = next;
+                        low[w] = next;
+                        next += 1;
+                        stack.push(w);
+                        on_stack[w] = true;
+                        call.push((w, 0));
+                    } else if on_stack[w] {
+                        low[v] = low[v].min(index[w]);
+                    }
+                } else {
+                    call.pop();
+                    if let Some(&(u, _)) = call.last() {
+                        low[u] = low[u].min(low[v]);
+                    }
+                    if low[v] == index[v] {
+                        let mut comp = Vec::new();
+                        loop {
+                            let w = stack.pop().unwrap();
+                            on_stack[w] = false;
+                            comp.push(w);
+                            if w == v {
+                                break;
+                            }
+                        }
+                        ret.push(comp);
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    /* a component carries a cycle if it has more than one vertex or a self loop */
+    pub fn is_cyclic(&self, comp: &[usize]) -> bool {
+        comp.len() > 1 || self.has_edge(comp[0], comp[0])
+    }
 }
@@ -0,0 +1,70 @@
+use crate::process::*;
+use std::fmt;
+
+/* a buffered channel, kept in State. a put is enabled while fewer than
+ * `capacity` messages wait and a get while some do. a lossy channel may
+ * drop a message as it is put, a reordering one hands out any waiting
+ * message instead of the oldest.
+ */
+#[derive(Default, std::fmt::Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Queue {
+    pub name: String,
+    pub capacity: usize,
+    pub lossy: bool,
+    pub reorder: bool,
+    /* oldest first */
+    pub msgs: Vec<Msg>,
+}
+
+impl Queue {
+    pub fn new(name: &str, capacity: usize) -> Queue {
+        Queue {
+            name: name.to_string(),
+            capacity,
+            lossy: false,
+            reorder: false,
+            msgs: Vec::new(),
+        }
+    }
+
+    pub fn lossy(mut self) -> Queue {
+        self.lossy = true;
+        self
+    }
+
+    pub fn reorder(mut self) -> Queue {
+        self.reorder = true;
+        self
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.msgs.len() >= self.capacity
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.msgs.is_empty()
+    }
+
+    /* positions a get may take a message from, one per distinct value */
+    pub(crate) fn takeable(&self) -> Vec<usize> {
+        if !self.reorder {
+            return if self.is_empty() { vec![] } else { vec![0] };
+        }
+        (0..self.msgs.len())
+            .filter(|i| !self.msgs[..*i].contains(&self.msgs[*i]))
+            .collect()
+    }
+}
+
+impl fmt::Display for Queue {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}=[", self.name)?;
+        for (i, m) in self.msgs.iter().enumerate() {
+            if i > 0 {
+                write!(fmt, " ")?;
+            }
+            write!(fmt, "{}", m)?;
+        }
+        write!(fmt, "]")
+    }
+}
@@ -0,0 +1,302 @@
+use crate::codec::*;
+use crate::lts::*;
+use crate::process::*;
+use crate::search::*;
+use std::fs;
+use std::hash::Hash;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8] = b"rddsv checkpoint 5\n";
+
+/* a snapshot is written to `path` after every `every` expanded states.
+ * the last one is left behind when the exploration ends.
+ */
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub path: PathBuf,
+    pub every: usize,
+}
+
+impl Checkpoint {
+    pub fn new(path: &Path, every: usize) -> Checkpoint {
+        Checkpoint {
+            path: path.to_path_buf(),
+            every: every.max(1),
+        }
+    }
+}
+
+impl Codec for Limit {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let tag: u8 = match self {
+            Limit::States => 0,
+            Limit::Depth => 1,
+            Limit::Trans => 2,
+            Limit::Time => 3,
+            Limit::Error => 4,
+        };
+        tag.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        match u8::decode(input)? {
+            0 => Some(Limit::States),
+            1 => Some(Limit::Depth),
+            2 => Some(Limit::Trans),
+            3 => Some(Limit::Time),
+            4 => Some(Limit::Error),
+            _ => None,
+        }
+    }
+}
+
+impl Codec for Fault {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Fault::Deadlock => 0u8.encode(out),
+            Fault::Invariant(name) => {
+                1u8.encode(out);
+                name.encode(out);
+            }
+            Fault::Assertion(l, loc) => {
+                2u8.encode(out);
+                l.0.encode(out);
+                loc.encode(out);
+            }
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        match u8::decode(input)? {
+            0 => Some(Fault::Deadlock),
+            1 => Some(Fault::Invariant(String::decode(input)?)),
+            2 => Some(Fault::Assertion(
+                Label(String::decode(input)?),
+                Location::decode(input)?,
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl Codec for CompTrans {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.label.encode(out);
+        self.before.encode(out);
+        self.after.encode(out);
+        self.process.encode(out);
+        self.partners.encode(out);
+        self.msg.encode(out);
+        self.progress.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let mut ct = CompTrans::new(
+            String::decode(input)?,
+            usize::decode(input)?,
+            usize::decode(input)?,
+        );
+        ct.process = usize::decode(input)?;
+        ct.partners = Vec::decode(input)?;
+        ct.msg = Option::decode(input)?;
+        ct.progress = bool::decode(input)?;
+        Some(ct)
+    }
+}
+
+fn save<T: Codec + Clone + Eq + Hash, L: Local + Codec>(
+    search: &Search<T, L>,
+    path: &Path,
+) -> io::Result<()> {
+    let lts = &search.lts;
+    let mut out = MAGIC.to_vec();
+    let labels: Vec<String> = lts.labels.iter().map(|l| l.0.clone()).collect();
+    labels.encode(&mut out);
+    lts.hat.len().encode(&mut out);
+    for (s, _) in lts.hat.iter() {
+        s.encode(&mut out);
+    }
+    lts.parent.encode(&mut out);
+    lts.trans.encode(&mut out);
+    search.depth.encode(&mut out);
+    search.frontier.save(&mut out);
+    search.partial.limits.encode(&mut out);
+    search.partial.frontier.encode(&mut out);
+    search.stopped.encode(&mut out);
+    lts.faults.len().encode(&mut out);
+    for (f, v) in &lts.faults {
+        f.encode(&mut out);
+        v.state.encode(&mut out);
+    }
+
+    /* never leave a torn snapshot behind */
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, out)?;
+    fs::rename(&tmp, path)
+}
+
+fn load<T, L: Local + Codec>(
+    process: &[Process<T, L>],
+    config: &SearchConfig<T, L>,
+    path: &Path,
+) -> io::Result<Search<T, L>>
+where
+    T: std::fmt::Display + Clone + Copy + Eq + Hash + Codec,
+{
+    let bytes = fs::read(path)?;
+    if !bytes.starts_with(MAGIC) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a checkpoint",
+        ));
+    }
+    let mut input = &bytes[MAGIC.len()..];
+    decode_search(process, config, &mut input)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "broken or foreign checkpoint"))
+}
+
+fn decode_search<T, L: Local + Codec>(
+    process: &[Process<T, L>],
+    config: &SearchConfig<T, L>,
+    input: &mut &[u8],
+) -> Option<Search<T, L>>
+where
+    T: std::fmt::Display + Clone + Copy + Eq + Hash + Codec,
+{
+    let mut search = Search::empty(process, config);
+    let labels = Vec::<String>::decode(input)?;
+    if labels.len() != process.len() || labels.iter().zip(process).any(|(l, p)| *l != p.label.0) {
+        return None;
+    }
+    let states: Vec<State<T, L>> = (0..usize::decode(input)?)
+        .map(|_| State::decode(input))
+        .collect::<Option<_>>()?;
+    let n = states.len();
+    let fits = |s: &State<T, L>| {
+        s.locations.len() == process.len()
+            && s.locals.len() == process.len()
+            && s.locations
+                .iter()
+                .zip(process)
+                .all(|(l, p)| l.to_usize() < p.v.len())
+    };
+    if n == 0 || !states.iter().all(fits) {
+        return None;
+    }
+    /* every state holds the queues of the first, in the same order and
+     * within their capacity, and the processes use no other
+     */
+    let first = &states[0].channels;
+    let same = |s: &State<T, L>| {
+        s.channels.len() == first.len()
+            && s.channels.iter().zip(first).all(|(q, f)| {
+                q.name == f.name
+                    && q.capacity == f.capacity
+                    && q.lossy == f.lossy
+                    && q.reorder == f.reorder
+                    && q.msgs.len() <= q.capacity
+            })
+    };
+    let declared = |t: &ProcessTrans<T, L>| match &t.comm {
+        Some(Comm::Put(c, _)) | Some(Comm::Get(c, _)) => first.iter().any(|q| q.name == *c),
+        _ => true,
+    };
+    let mut transs = process.iter().flat_map(|p| &p.v).flat_map(|u| &u.transs);
+    if !states.iter().all(same) || !transs.all(declared) {
+        return None;
+    }
+    let parent = Vec::<Option<usize>>::decode(input)?;
+    let trans = Vec::<CompTrans>::decode(input)?;
+    let known = |t: &CompTrans| {
+        t.before < n
+            && t.after < n
+            && t.process < process.len()
+            && t.partners.iter().all(|j| *j < process.len())
+    };
+    if !trans.iter().all(known) || parent.len() != n || parent[0].is_some() {
+        return None;
+    }
+    /* every parent leads to its state from one found earlier, so the
+     * shortest paths end
+     */
+    for (id, p) in parent.iter().enumerate().skip(1) {
+        match p.map(|i| trans.get(i)) {
+            Some(Some(t)) if t.after == id && t.before < id => {}
+            _ => return None,
+        }
+    }
+    for (s, p) in states.into_iter().zip(parent) {
+        search.lts.add_state(process, s, p);
+    }
+    if search.lts.len() != n {
+        return None;
+    }
+    search.lts.trans = trans;
+    search.depth = Vec::decode(input)?;
+    if search.depth.len() != n {
+        return None;
+    }
+    search.frontier = Frontier::load(&config.strategy, input, n)?;
+    search.partial.limits = Vec::decode(input)?;
+    search.partial.frontier = Vec::decode(input)?;
+    if search.partial.frontier.iter().any(|id| *id >= n) {
+        return None;
+    }
+    search.stopped = bool::decode(input)?;
+    for _ in 0..usize::decode(input)? {
+        let f = Fault::decode(input)?;
+        let id = usize::decode(input)?;
+        if id >= n {
+            return None;
+        }
+        search.lts.fault(f, id);
+    }
+    Some(search)
+}
+
+fn run<T, L: Local + Codec>(
+    process: &[Process<T, L>],
+    config: &SearchConfig<T, L>,
+    mut search: Search<T, L>,
+    ck: &Checkpoint,
+) -> io::Result<Lts<T, L>>
+where
+    T: std::fmt::Display + Clone + Copy + Eq + Hash + Codec,
+{
+    while !search.run(process, config, Some(ck.every)) {
+        save(&search, &ck.path)?;
+    }
+    Ok(search.finish())
+}
+
+/* concurrent_composition_with, saving snapshots as it goes */
+pub fn concurrent_composition_checkpoint<T, L: Local + Codec>(
+    process: Vec<Process<T, L>>,
+    s0: State<T, L>,
+    config: &SearchConfig<T, L>,
+    ck: &Checkpoint,
+) -> io::Result<Lts<T, L>>
+where
+    T: std::fmt::Display + Clone + Copy + Eq + Hash + Codec,
+{
+    let search = Search::start(&process, s0, config);
+    run(&process, config, search, ck)
+}
+
+/* carry on from the snapshot at `ck.path`. the processes and the search
+ * strategy must be those of the interrupted run, a snapshot taken under
+ * another kind of strategy or naming other processes or queues is
+ * InvalidData; a time limit counts from the resume.
+ */
+pub fn resume<T, L: Local + Codec>(
+    process: Vec<Process<T, L>>,
+    config: &SearchConfig<T, L>,
+    ck: &Checkpoint,
+) -> io::Result<Lts<T, L>>
+where
+    T: std::fmt::Display + Clone + Copy + Eq + Hash + Codec,
+{
+    let search = load(&process, config, &ck.path)?;
+    run(&process, config, search, ck)
+}
@@ -0,0 +1,175 @@
+use crate::channel::*;
+use crate::lts::*;
+use crate::process::*;
+use std::convert::TryInto;
+
+/* a compact byte encoding, for the states written to disk.
+ * implement it for the shared variables of a model by encoding each
+ * field in turn:
+ *
+ *     impl Codec for SharedVars {
+ *         fn encode(&self, out: &mut Vec<u8>) {
+ *             self.x.encode(out);
+ *         }
+ *         fn decode(input: &mut &[u8]) -> Option<Self> {
+ *             Some(SharedVars { x: i32::decode(input)? })
+ *         }
+ *     }
+ */
+pub trait Codec: Sized {
+    fn encode(&self, out: &mut Vec<u8>);
+    /* read a value off the front of `input`, None when it runs short */
+    fn decode(input: &mut &[u8]) -> Option<Self>;
+}
+
+fn take<'a>(input: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if input.len() < n {
+        return None;
+    }
+    let (head, rest) = input.split_at(n);
+    *input = rest;
+    Some(head)
+}
+
+macro_rules! codec_int {
+    ($($t:ty),*) => {
+        $(
+            impl Codec for $t {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(input: &mut &[u8]) -> Option<Self> {
+                    let b = take(input, std::mem::size_of::<$t>())?;
+                    Some(<$t>::from_le_bytes(b.try_into().ok()?))
+                }
+            }
+        )*
+    };
+}
+
+codec_int!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+impl Codec for usize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(u64::decode(input)? as usize)
+    }
+}
+
+impl Codec for () {
+    fn encode(&self, _out: &mut Vec<u8>) {}
+
+    fn decode(_input: &mut &[u8]) -> Option<Self> {
+        Some(())
+    }
+}
+
+impl Codec for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(u8::decode(input)? != 0)
+    }
+}
+
+impl Codec for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        out.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let n = usize::decode(input)?;
+        String::from_utf8(take(input, n)?.to_vec()).ok()
+    }
+}
+
+impl<T: Codec> Codec for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.len().encode(out);
+        for v in self {
+            v.encode(out);
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        let n = usize::decode(input)?;
+        (0..n).map(|_| T::decode(input)).collect()
+    }
+}
+
+impl<T: Codec> Codec for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Some(v) => {
+                true.encode(out);
+                v.encode(out);
+            }
+            None => false.encode(out),
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        if bool::decode(input)? {
+            Some(Some(T::decode(input)?))
+        } else {
+            Some(None)
+        }
+    }
+}
+
+impl Codec for Location {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.to_usize().encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(Location::new(usize::decode(input)?))
+    }
+}
+
+impl Codec for Queue {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.name.encode(out);
+        self.capacity.encode(out);
+        self.lossy.encode(out);
+        self.reorder.encode(out);
+        self.msgs.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(Queue {
+            name: String::decode(input)?,
+            capacity: usize::decode(input)?,
+            lossy: bool::decode(input)?,
+            reorder: bool::decode(input)?,
+            msgs: Vec::decode(input)?,
+        })
+    }
+}
+
+impl<T: Codec, L: Codec> Codec for State<T, L> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.shared_vars.encode(out);
+        self.locations.encode(out);
+        self.locals.encode(out);
+        self.channels.encode(out);
+        self.deadlock.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Option<Self> {
+        Some(State {
+            shared_vars: T::decode(input)?,
+            locations: Vec::decode(input)?,
+            locals: Vec::decode(input)?,
+            channels: Vec::decode(input)?,
+            deadlock: bool::decode(input)?,
+        })
+    }
+}
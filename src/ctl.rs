@@ -0,0 +1,347 @@
+use crate::formula::*;
+use crate::lts::*;
+use crate::process::Local;
+use std::collections::VecDeque;
+use std::fmt;
+use std::hash::Hash;
+use std::str::FromStr;
+
+/* computation tree logic over the states of a composed Lts.
+ * syntax: `!`, `&&`, `||`, `->`, `EX`, `AX`, `EF`, `AF`, `EG`, `AG`,
+ * `E(a U b)`, `A(a U b)`, `true`, `false`, `P@2` and predicate names,
+ * e.g. `AG EF (P@0 && Q@0)`.
+ * terminal states are taken to loop on themselves, as in check_ltl.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Ctl {
+    True,
+    False,
+    Ap(Atom),
+    Not(Box<Ctl>),
+    And(Box<Ctl>, Box<Ctl>),
+    Or(Box<Ctl>, Box<Ctl>),
+    Implies(Box<Ctl>, Box<Ctl>),
+    EX(Box<Ctl>),
+    AX(Box<Ctl>),
+    EF(Box<Ctl>),
+    AF(Box<Ctl>),
+    EG(Box<Ctl>),
+    AG(Box<Ctl>),
+    EU(Box<Ctl>, Box<Ctl>),
+    AU(Box<Ctl>, Box<Ctl>),
+}
+
+impl fmt::Display for Ctl {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ctl::True => write!(fmt, "true"),
+            Ctl::False => write!(fmt, "false"),
+            Ctl::Ap(a) => write!(fmt, "{}", a),
+            Ctl::Not(a) => write!(fmt, "!{}", a),
+            Ctl::And(a, b) => write!(fmt, "({} && {})", a, b),
+            Ctl::Or(a, b) => write!(fmt, "({} || {})", a, b),
+            Ctl::Implies(a, b) => write!(fmt, "({} -> {})", a, b),
+            Ctl::EX(a) => write!(fmt, "EX {}", a),
+            Ctl::AX(a) => write!(fmt, "AX {}", a),
+            Ctl::EF(a) => write!(fmt, "EF {}", a),
+            Ctl::AF(a) => write!(fmt, "AF {}", a),
+            Ctl::EG(a) => write!(fmt, "EG {}", a),
+            Ctl::AG(a) => write!(fmt, "AG {}", a),
+            Ctl::EU(a, b) => write!(fmt, "E({} U {})", a, b),
+            Ctl::AU(a, b) => write!(fmt, "A({} U {})", a, b),
+        }
+    }
+}
+
+impl FromStr for Ctl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ctl::parse(s)
+    }
+}
+
+impl Ctl {
+    pub fn parse(s: &str) -> Result<Ctl, String> {
+        let mut tokens = Tokens::new(s)?;
+        let ret = Ctl::parse_implies(&mut tokens)?;
+        if !tokens.is_end() {
+            return Err(format!("unexpected {:?}", tokens.peek().unwrap()));
+        }
+        Ok(ret)
+    }
+
+    fn parse_implies(tokens: &mut Tokens) -> Result<Ctl, String> {
+        let lhs = Ctl::parse_or(tokens)?;
+        if tokens.peek() == Some(&Token::Implies) {
+            tokens.next();
+            let rhs = Ctl::parse_implies(tokens)?;
+            return Ok(Ctl::Implies(Box::new(lhs), Box::new(rhs)));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_or(tokens: &mut Tokens) -> Result<Ctl, String> {
+        let mut lhs = Ctl::parse_and(tokens)?;
+        while tokens.peek() == Some(&Token::Or) {
+            tokens.next();
+            let rhs = Ctl::parse_and(tokens)?;
+            lhs = Ctl::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_and(tokens: &mut Tokens) -> Result<Ctl, String> {
+        let mut lhs = Ctl::parse_unary(tokens)?;
+        while tokens.peek() == Some(&Token::And) {
+            tokens.next();
+            let rhs = Ctl::parse_unary(tokens)?;
+            lhs = Ctl::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(tokens: &mut Tokens) -> Result<Ctl, String> {
+        let b = Box::new;
+        match tokens.next() {
+            Some(Token::Not) => Ok(Ctl::Not(b(Ctl::parse_unary(tokens)?))),
+            Some(Token::LParen) => {
+                let ret = Ctl::parse_implies(tokens)?;
+                tokens.expect(Token::RParen)?;
+                Ok(ret)
+            }
+            Some(Token::At(l, loc)) => Ok(Ctl::Ap(Atom::At(l, loc))),
+            Some(Token::Ident(s)) => match s.as_str() {
+                "true" => Ok(Ctl::True),
+                "false" => Ok(Ctl::False),
+                "EX" => Ok(Ctl::EX(b(Ctl::parse_unary(tokens)?))),
+                "AX" => Ok(Ctl::AX(b(Ctl::parse_unary(tokens)?))),
+                "EF" => Ok(Ctl::EF(b(Ctl::parse_unary(tokens)?))),
+                "AF" => Ok(Ctl::AF(b(Ctl::parse_unary(tokens)?))),
+                "EG" => Ok(Ctl::EG(b(Ctl::parse_unary(tokens)?))),
+                "AG" => Ok(Ctl::AG(b(Ctl::parse_unary(tokens)?))),
+                "E" | "A" => {
+                    tokens.expect(Token::LParen)?;
+                    let lhs = Ctl::parse_implies(tokens)?;
+                    tokens.expect(Token::Ident("U".to_string()))?;
+                    let rhs = Ctl::parse_implies(tokens)?;
+                    tokens.expect(Token::RParen)?;
+                    if s == "E" {
+                        Ok(Ctl::EU(b(lhs), b(rhs)))
+                    } else {
+                        Ok(Ctl::AU(b(lhs), b(rhs)))
+                    }
+                }
+                _ => Ok(Ctl::Ap(Atom::Pred(s))),
+            },
+            Some(t) => Err(format!("unexpected {:?}", t)),
+            None => Err("unexpected end of formula".to_string()),
+        }
+    }
+
+    pub fn atoms(&self) -> Vec<Atom> {
+        let mut ret = Vec::new();
+        self.collect_atoms(&mut ret);
+        ret
+    }
+
+    fn collect_atoms(&self, v: &mut Vec<Atom>) {
+        match self {
+            Ctl::True | Ctl::False => {}
+            Ctl::Ap(a) => {
+                if !v.contains(a) {
+                    v.push(a.clone());
+                }
+            }
+            Ctl::Not(a)
+            | Ctl::EX(a)
+            | Ctl::AX(a)
+            | Ctl::EF(a)
+            | Ctl::AF(a)
+            | Ctl::EG(a)
+            | Ctl::AG(a) => a.collect_atoms(v),
+            Ctl::And(a, b) | Ctl::Or(a, b) | Ctl::Implies(a, b) | Ctl::EU(a, b) | Ctl::AU(a, b) => {
+                a.collect_atoms(v);
+                b.collect_atoms(v);
+            }
+        }
+    }
+}
+
+/* successor and predecessor lists, terminal states looping on themselves */
+struct Graph {
+    succ: Vec<Vec<StateId>>,
+    pred: Vec<Vec<StateId>>,
+}
+
+impl Graph {
+    fn ex(&self, a: &[bool]) -> Vec<bool> {
+        self.succ.iter().map(|v| v.iter().any(|t| a[*t])).collect()
+    }
+
+    /* least fixpoint: b, or a with some successor in the set */
+    fn eu(&self, a: &[bool], b: &[bool]) -> Vec<bool> {
+        let mut ret = b.to_vec();
+        let mut que: VecDeque<StateId> = (0..b.len()).filter(|s| b[*s]).collect();
+        while let Some(t) = que.pop_front() {
+            for s in &self.pred[t] {
+                if !ret[*s] && a[*s] {
+                    ret[*s] = true;
+                    que.push_back(*s);
+                }
+            }
+        }
+        ret
+    }
+
+    /* greatest fixpoint: a, with some successor in the set */
+    fn eg(&self, a: &[bool]) -> Vec<bool> {
+        let mut ret = a.to_vec();
+        let mut count: Vec<usize> = self
+            .succ
+            .iter()
+            .map(|v| v.iter().filter(|t| a[**t]).count())
+            .collect();
+        let mut que: VecDeque<StateId> = (0..a.len()).filter(|s| a[*s] && count[*s] == 0).collect();
+        for s in &que {
+            ret[*s] = false;
+        }
+        while let Some(t) = que.pop_front() {
+            for s in &self.pred[t] {
+                if ret[*s] {
+                    count[*s] -= 1;
+                    if count[*s] == 0 {
+                        ret[*s] = false;
+                        que.push_back(*s);
+                    }
+                }
+            }
+        }
+        ret
+    }
+}
+
+fn not(a: &[bool]) -> Vec<bool> {
+    a.iter().map(|x| !x).collect()
+}
+
+impl<T: std::fmt::Display + Clone + Eq + Hash, L: Local> Lts<T, L> {
+    fn ctl_graph(&self) -> Graph {
+        let mut succ = self.get_ali();
+        for (s, v) in succ.iter_mut().enumerate() {
+            if v.is_empty() {
+                v.push(s);
+            }
+        }
+        let mut pred = vec![vec![]; succ.len()];
+        for (s, v) in succ.iter().enumerate() {
+            for t in v {
+                pred[*t].push(s);
+            }
+        }
+        Graph { succ, pred }
+    }
+
+    fn ctl_sat(
+        &self,
+        g: &Graph,
+        f: &Ctl,
+        preds: &[(&str, Predicate<T>)],
+        memo: &mut Vec<(Ctl, Vec<bool>)>,
+    ) -> Vec<bool> {
+        if let Some((_, v)) = memo.iter().find(|(c, _)| c == f) {
+            return v.clone();
+        }
+        let n = self.hat.len();
+        let ret = match f {
+            Ctl::True => vec![true; n],
+            Ctl::False => vec![false; n],
+            Ctl::Ap(a) => (0..n).map(|s| self.eval_atom(a, s, preds)).collect(),
+            Ctl::Not(a) => not(&self.ctl_sat(g, a, preds, memo)),
+            Ctl::And(a, b) => {
+                let (x, y) = (
+                    self.ctl_sat(g, a, preds, memo),
+                    self.ctl_sat(g, b, preds, memo),
+                );
+                x.iter().zip(y.iter()).map(|(p, q)| *p && *q).collect()
+            }
+            Ctl::Or(a, b) => {
+                let (x, y) = (
+                    self.ctl_sat(g, a, preds, memo),
+                    self.ctl_sat(g, b, preds, memo),
+                );
+                x.iter().zip(y.iter()).map(|(p, q)| *p || *q).collect()
+            }
+            Ctl::Implies(a, b) => {
+                let (x, y) = (
+                    self.ctl_sat(g, a, preds, memo),
+                    self.ctl_sat(g, b, preds, memo),
+                );
+                x.iter().zip(y.iter()).map(|(p, q)| !*p || *q).collect()
+            }
+            Ctl::EX(a) => g.ex(&self.ctl_sat(g, a, preds, memo)),
+            Ctl::AX(a) => not(&g.ex(&not(&self.ctl_sat(g, a, preds, memo)))),
+            Ctl::EF(a) => g.eu(&vec![true; n], &self.ctl_sat(g, a, preds, memo)),
+            Ctl::AF(a) => not(&g.eg(&not(&self.ctl_sat(g, a, preds, memo)))),
+            Ctl::EG(a) => g.eg(&self.ctl_sat(g, a, preds, memo)),
+            Ctl::AG(a) => not(&g.eu(&vec![true; n], &not(&self.ctl_sat(g, a, preds, memo)))),
+            Ctl::EU(a, b) => {
+                let (x, y) = (
+                    self.ctl_sat(g, a, preds, memo),
+                    self.ctl_sat(g, b, preds, memo),
+                );
+                g.eu(&x, &y)
+            }
+            Ctl::AU(a, b) => {
+                /* A(a U b) = !(E(!b U (!a && !b)) || EG !b) */
+                let (x, y) = (
+                    self.ctl_sat(g, a, preds, memo),
+                    self.ctl_sat(g, b, preds, memo),
+                );
+                let nb = not(&y);
+                let stop: Vec<bool> = x.iter().zip(nb.iter()).map(|(p, q)| !*p && *q).collect();
+                let eu = g.eu(&nb, &stop);
+                let eg = g.eg(&nb);
+                eu.iter().zip(eg.iter()).map(|(p, q)| !(*p || *q)).collect()
+            }
+        };
+        memo.push((f.clone(), ret.clone()));
+        ret
+    }
+
+    /* the states satisfying `f`; it holds for the Lts when 0 is among them */
+    pub fn check_ctl(
+        &self,
+        f: &Ctl,
+        preds: &[(&str, Predicate<T>)],
+    ) -> Result<Vec<StateId>, String> {
+        self.check_atoms(&f.atoms(), preds)?;
+        let g = self.ctl_graph();
+        let sat = self.ctl_sat(&g, f, preds, &mut Vec::new());
+        Ok((0..sat.len()).filter(|s| sat[*s]).collect())
+    }
+
+    /* the states satisfying every subformula of `f`, innermost first */
+    pub fn ctl_subformulas(
+        &self,
+        f: &Ctl,
+        preds: &[(&str, Predicate<T>)],
+    ) -> Result<Vec<(Ctl, Vec<StateId>)>, String> {
+        self.check_atoms(&f.atoms(), preds)?;
+        let g = self.ctl_graph();
+        let mut memo = Vec::new();
+        self.ctl_sat(&g, f, preds, &mut memo);
+        Ok(memo
+            .into_iter()
+            .map(|(c, sat)| (c, (0..sat.len()).filter(|s| sat[*s]).collect()))
+            .collect())
+    }
+
+    /* satisfying states are drawn in blue, the others in red */
+    pub fn mark_ctl(&mut self, sat: &[StateId]) {
+        self.verdict = vec![Some(false); self.hat.len()];
+        for s in sat {
+            self.verdict[*s] = Some(true);
+        }
+    }
+}
@@ -0,0 +1,338 @@
+use crate::codec::*;
+use crate::lts::*;
+use crate::process::*;
+use crate::search::*;
+use crate::store::*;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::fs::File;
+use std::hash::Hash;
+use std::io;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/* rough bytes a state takes in a hash set besides its encoding */
+const OVERHEAD: usize = 64;
+
+/* a parents record: the id of the parent and the interned label */
+const PARENT_LEN: u64 = 16;
+const ROOT: u64 = u64::MAX;
+
+fn invalid(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, what.to_string())
+}
+
+fn write_record<W: Write>(w: &mut W, id: u64, bytes: &[u8]) -> io::Result<()> {
+    w.write_all(&id.to_le_bytes())?;
+    w.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    w.write_all(bytes)
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut b = [0; 8];
+    r.read_exact(&mut b)?;
+    Ok(u64::from_le_bytes(b))
+}
+
+/* the next (id, encoded state) of a frontier or visited file */
+fn read_record<R: Read>(r: &mut R) -> io::Result<Option<(u64, Vec<u8>)>> {
+    let id = match read_u64(r) {
+        Ok(id) => id,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let len = read_u64(r)?;
+    let mut bytes = vec![0; len as usize];
+    r.read_exact(&mut bytes)?;
+    Ok(Some((id, bytes)))
+}
+
+fn decode_state<T: Codec, L: Local + Codec>(bytes: &[u8]) -> io::Result<State<T, L>> {
+    State::decode(&mut &bytes[..]).ok_or_else(|| invalid("broken state record"))
+}
+
+/* successors of one level waiting for duplicate detection, in the order
+ * they were generated
+ */
+struct Batch<T, L> {
+    states: Vec<(State<T, L>, u64, u64)>,
+    seen: HashSet<State<T, L>>,
+    bytes: usize,
+}
+
+impl<T: Clone + Eq + Hash, L: Local + Codec> Batch<T, L> {
+    fn new() -> Batch<T, L> {
+        Batch {
+            states: Vec::new(),
+            seen: HashSet::new(),
+            bytes: 0,
+        }
+    }
+}
+
+/* the visited states: those found lately in memory, the rest in a file
+ * that is read through once per batch
+ */
+struct Spill<T, L> {
+    dir: PathBuf,
+    memory: usize,
+    cache: HashSet<State<T, L>>,
+    cache_bytes: usize,
+    visited_len: u64,
+    parents: BufWriter<File>,
+    labels: Vec<String>,
+    label_ids: HashMap<String, u64>,
+    count: u64,
+}
+
+impl<T: Clone + Eq + Hash + Codec, L: Local + Codec> Spill<T, L> {
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    fn intern(&mut self, label: String) -> u64 {
+        if let Some(id) = self.label_ids.get(&label) {
+            return *id;
+        }
+        let id = self.labels.len() as u64;
+        self.labels.push(label.clone());
+        self.label_ids.insert(label, id);
+        id
+    }
+
+    /* number a new state, remembering how it was reached */
+    fn add(&mut self, s: &State<T, L>, parent: u64, label: u64) -> io::Result<u64> {
+        let id = self.count;
+        self.count += 1;
+        self.parents.write_all(&parent.to_le_bytes())?;
+        self.parents.write_all(&label.to_le_bytes())?;
+        let mut bytes = Vec::new();
+        s.encode(&mut bytes);
+        self.cache_bytes += bytes.len() + OVERHEAD;
+        self.cache.insert(s.clone());
+        if self.cache_bytes > self.memory / 2 {
+            self.spill()?;
+        }
+        Ok(id)
+    }
+
+    /* move the cached states to the visited file */
+    fn spill(&mut self) -> io::Result<()> {
+        let f = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path("visited"))?;
+        let mut w = BufWriter::new(f);
+        for s in self.cache.drain() {
+            let mut bytes = Vec::new();
+            s.encode(&mut bytes);
+            write_record(&mut w, 0, &bytes)?;
+            self.visited_len += 1;
+        }
+        self.cache_bytes = 0;
+        w.flush()
+    }
+
+    /* drop the states of `batch` visited already, returning how many */
+    fn dedup(&mut self, batch: &mut Batch<T, L>) -> io::Result<usize> {
+        let before = batch.states.len();
+        let cache = &self.cache;
+        batch.seen.retain(|s| !cache.contains(s));
+        if self.visited_len > 0 {
+            let mut r = BufReader::new(File::open(self.path("visited"))?);
+            while let Some((_, bytes)) = read_record(&mut r)? {
+                batch.seen.remove(&decode_state::<T, L>(&bytes)?);
+            }
+        }
+        let seen = &batch.seen;
+        batch.states.retain(|(s, _, _)| seen.contains(s));
+        Ok(before - batch.states.len())
+    }
+
+    /* labels of the transitions from the initial state to `id` */
+    fn trace(&mut self, mut id: u64) -> io::Result<Vec<String>> {
+        self.parents.flush()?;
+        let mut f = File::open(self.path("parents"))?;
+        let mut path = Vec::new();
+        loop {
+            f.seek(SeekFrom::Start(id * PARENT_LEN))?;
+            let parent = read_u64(&mut f)?;
+            let label = read_u64(&mut f)?;
+            if parent == ROOT {
+                break;
+            }
+            path.push(self.labels[label as usize].clone());
+            id = parent;
+        }
+        path.reverse();
+        Ok(path)
+    }
+}
+
+/* breadth first search with delayed duplicate detection, keeping about
+ * `memory` bytes of states in memory and the rest in files under `dir`.
+ * successors of a level are gathered in a batch until half the budget is
+ * used, then checked against the visited states in one pass over the
+ * visited file. states are numbered as concurrent_composition numbers
+ * them, so traces are the same shortest ones.
+ * no Lts is built; the checks of `config`, stop_on_error and max_depth
+ * are used, the other settings are not. the files are removed at the end.
+ */
+pub fn coverage_on_disk<T, L: Local + Codec>(
+    process: Vec<Process<T, L>>,
+    mut s0: State<T, L>,
+    config: &SearchConfig<T, L>,
+    dir: &Path,
+    memory: usize,
+) -> io::Result<Coverage<T, L>>
+where
+    T: std::fmt::Display + Clone + Copy + Eq + Hash + Codec,
+{
+    init_state(&process, &mut s0);
+    fs::create_dir_all(dir)?;
+    let mut spill = Spill {
+        dir: dir.to_path_buf(),
+        memory,
+        cache: HashSet::new(),
+        cache_bytes: 0,
+        visited_len: 0,
+        parents: BufWriter::new(File::create(dir.join("parents"))?),
+        labels: Vec::new(),
+        label_ids: HashMap::new(),
+        count: 0,
+    };
+    let _ = fs::remove_file(spill.path("visited"));
+    let mut cov = Coverage {
+        states: 0,
+        trans: 0,
+        hits: 0,
+        depth: 0,
+        omitted: 0.0,
+        omission: 0.0,
+        faults: Vec::new(),
+    };
+
+    let (mut cur, mut next) = (spill.path("frontier0"), spill.path("frontier1"));
+    let mut w = BufWriter::new(File::create(&cur)?);
+    let mut bytes = Vec::new();
+    s0.encode(&mut bytes);
+    write_record(&mut w, 0, &bytes)?;
+    w.flush()?;
+    spill.add(&s0, ROOT, 0)?;
+    cov.states += 1;
+    let mut stop = false;
+    for fault in state_faults(&process, &s0, config) {
+        cov.faults.push(Counterexample {
+            fault,
+            state: s0.clone(),
+            path: Vec::new(),
+        });
+        stop = config.stop_on_error;
+    }
+
+    let mut depth = 0;
+    while !stop {
+        let mut r = BufReader::new(File::open(&cur)?);
+        let mut w = BufWriter::new(File::create(&next)?);
+        let mut batch = Batch::new();
+        let mut found = 0;
+        let cut = config.max_depth.is_some_and(|d| depth >= d);
+        while let Some((id, bytes)) = read_record(&mut r)? {
+            let s: State<T, L> = decode_state(&bytes)?;
+            let succ = if cut {
+                Vec::new()
+            } else {
+                successors(&process, &s)
+            };
+            if config.deadlock && !cut && succ.is_empty() && !is_end(&process, &s) {
+                cov.faults.push(Counterexample {
+                    fault: Fault::Deadlock,
+                    state: s.clone(),
+                    path: spill.trace(id)?,
+                });
+                if config.stop_on_error {
+                    stop = true;
+                    break;
+                }
+            }
+            for n in succ {
+                cov.trans += 1;
+                let label = spill.intern(n.label);
+                if batch.seen.insert(n.state.clone()) {
+                    let mut bytes = Vec::new();
+                    n.state.encode(&mut bytes);
+                    batch.bytes += bytes.len() + OVERHEAD;
+                    batch.states.push((n.state, id, label));
+                } else {
+                    cov.hits += 1;
+                }
+            }
+            if batch.bytes > memory / 2 {
+                found += flush(
+                    &mut spill, &mut batch, &mut w, &process, config, &mut cov, &mut stop,
+                )?;
+                if stop {
+                    break;
+                }
+            }
+        }
+        if !stop {
+            found += flush(
+                &mut spill, &mut batch, &mut w, &process, config, &mut cov, &mut stop,
+            )?;
+        }
+        w.flush()?;
+        if found == 0 {
+            break;
+        }
+        depth += 1;
+        cov.depth = depth;
+        std::mem::swap(&mut cur, &mut next);
+    }
+
+    for name in &["frontier0", "frontier1", "visited", "parents"] {
+        let _ = fs::remove_file(spill.path(name));
+    }
+    Ok(cov)
+}
+
+/* number the new states of `batch` and queue them for the next level */
+fn flush<T, L: Local + Codec, W: Write>(
+    spill: &mut Spill<T, L>,
+    batch: &mut Batch<T, L>,
+    w: &mut W,
+    process: &[Process<T, L>],
+    config: &SearchConfig<T, L>,
+    cov: &mut Coverage<T, L>,
+    stop: &mut bool,
+) -> io::Result<usize>
+where
+    T: Clone + Copy + Eq + Hash + Codec,
+{
+    cov.hits += spill.dedup(batch)?;
+    let states = std::mem::take(&mut batch.states);
+    let found = states.len();
+    for (s, parent, label) in states {
+        let id = spill.add(&s, parent, label)?;
+        cov.states += 1;
+        let mut bytes = Vec::new();
+        s.encode(&mut bytes);
+        write_record(w, id, &bytes)?;
+        for fault in state_faults(process, &s, config) {
+            cov.faults.push(Counterexample {
+                fault,
+                state: s.clone(),
+                path: spill.trace(id)?,
+            });
+            if config.stop_on_error {
+                *stop = true;
+            }
+        }
+        if *stop {
+            break;
+        }
+    }
+    batch.seen.clear();
+    batch.bytes = 0;
+    Ok(found)
+}
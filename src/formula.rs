@@ -0,0 +1,170 @@
+use crate::lts::*;
+use crate::process::*;
+use std::fmt;
+use std::hash::Hash;
+
+/* a named predicate over the shared variables */
+pub type Predicate<T> = fn(&T) -> bool;
+
+/* atomic propositions of temporal formulas.
+ * `P@2` holds when process P sits at location 2,
+ * `name` holds when the predicate registered as `name` holds.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Atom {
+    At(String, usize),
+    Pred(String),
+}
+
+impl fmt::Display for Atom {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Atom::At(l, loc) => write!(fmt, "{}@{}", l, loc),
+            Atom::Pred(name) => write!(fmt, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Token {
+    LParen,
+    RParen,
+    Not,
+    And,
+    Or,
+    Implies,
+    Ident(String),
+    At(String, usize),
+}
+
+pub(crate) fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut ret = Vec::new();
+    let cs: Vec<char> = s.chars().collect();
+    let mut i = 0;
+    while i < cs.len() {
+        let c = cs[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let rest: String = cs[i..cs.len().min(i + 2)].iter().collect();
+        if rest == "&&" {
+            ret.push(Token::And);
+            i += 2;
+        } else if rest == "||" {
+            ret.push(Token::Or);
+            i += 2;
+        } else if rest == "->" {
+            ret.push(Token::Implies);
+            i += 2;
+        } else if c == '(' {
+            ret.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            ret.push(Token::RParen);
+            i += 1;
+        } else if c == '!' {
+            ret.push(Token::Not);
+            i += 1;
+        } else if c.is_alphanumeric() || c == '_' {
+            let start = i;
+            while i < cs.len() && (cs[i].is_alphanumeric() || cs[i] == '_') {
+                i += 1;
+            }
+            let ident: String = cs[start..i].iter().collect();
+            if i < cs.len() && cs[i] == '@' {
+                i += 1;
+                let start = i;
+                while i < cs.len() && cs[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let num: String = cs[start..i].iter().collect();
+                match num.parse::<usize>() {
+                    Ok(n) => ret.push(Token::At(ident, n)),
+                    Err(_) => return Err(format!("missing location after {}@", ident)),
+                }
+            } else {
+                ret.push(Token::Ident(ident));
+            }
+        } else {
+            return Err(format!("unexpected character '{}'", c));
+        }
+    }
+    Ok(ret)
+}
+
+/* cursor over a token list, shared by the formula parsers */
+pub(crate) struct Tokens {
+    v: Vec<Token>,
+    pos: usize,
+}
+
+impl Tokens {
+    pub(crate) fn new(s: &str) -> Result<Tokens, String> {
+        Ok(Tokens {
+            v: tokenize(s)?,
+            pos: 0,
+        })
+    }
+
+    pub(crate) fn peek(&self) -> Option<&Token> {
+        self.v.get(self.pos)
+    }
+
+    pub(crate) fn next(&mut self) -> Option<Token> {
+        let t = self.v.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    pub(crate) fn expect(&mut self, t: Token) -> Result<(), String> {
+        match self.next() {
+            Some(ref n) if *n == t => Ok(()),
+            Some(n) => Err(format!("expected {:?}, found {:?}", t, n)),
+            None => Err(format!("expected {:?}, found end of formula", t)),
+        }
+    }
+
+    pub(crate) fn is_end(&self) -> bool {
+        self.pos >= self.v.len()
+    }
+}
+
+impl<T: std::fmt::Display + Clone + Eq + Hash, L: Local> Lts<T, L> {
+    /* make sure every atom names a process or a registered predicate */
+    pub(crate) fn check_atoms(
+        &self,
+        atoms: &[Atom],
+        preds: &[(&str, Predicate<T>)],
+    ) -> Result<(), String> {
+        for a in atoms {
+            match a {
+                Atom::At(l, _) => {
+                    if !self.labels.contains(&Label::new(l)) {
+                        return Err(format!("unknown process {}", l));
+                    }
+                }
+                Atom::Pred(name) => {
+                    if !preds.iter().any(|(n, _)| n == name) {
+                        return Err(format!("unknown predicate {}", name));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn eval_atom(&self, a: &Atom, id: StateId, preds: &[(&str, Predicate<T>)]) -> bool {
+        let s = self.state(id).unwrap();
+        match a {
+            Atom::At(l, loc) => match self.labels.iter().position(|x| x.0 == *l) {
+                Some(i) => s.locations[i] == Location::new(*loc),
+                None => false,
+            },
+            Atom::Pred(name) => match preds.iter().find(|(n, _)| n == name) {
+                Some((_, p)) => p(&s.shared_vars),
+                None => false,
+            },
+        }
+    }
+}
@@ -1,3 +1,16 @@
 pub mod process;
 pub mod lts;
-pub mod algorithm;
\ No newline at end of file
+pub mod algorithm;
+pub mod liveness;
+pub mod formula;
+pub mod ltl;
+pub mod ctl;
+pub mod search;
+pub mod parallel;
+mod por;
+pub mod symmetry;
+pub mod store;
+pub mod codec;
+pub mod disk;
+pub mod checkpoint;
+pub mod channel;
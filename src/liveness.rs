@@ -0,0 +1,495 @@
+use crate::algorithm::AdjacencyList;
+use crate::formula::*;
+use crate::ltl::Buchi;
+use crate::lts::*;
+use crate::process::*;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::hash::Hash;
+
+/* an infinite run: a path from the initial state followed by a loop back
+ * to the state the path ends in
+ */
+#[derive(Clone)]
+pub struct Lasso {
+    pub prefix: Vec<CompTrans>,
+    pub cycle: Vec<CompTrans>,
+}
+
+impl Lasso {
+    /* the state the loop starts and ends in */
+    pub fn entry(&self) -> StateId {
+        match self.cycle.first() {
+            Some(t) => t.before,
+            None => 0,
+        }
+    }
+}
+
+impl fmt::Display for Lasso {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}:", self.entry())?;
+        for t in &self.prefix {
+            write!(fmt, " {}", t.label)?;
+        }
+        write!(fmt, " [")?;
+        for t in &self.cycle {
+            write!(fmt, " {}", t.label)?;
+        }
+        write!(fmt, " ]")
+    }
+}
+
+/* scheduling assumptions for liveness checks.
+ * a weakly fair process that stays enabled must eventually move;
+ * a strongly fair transition ("P.CAS") enabled infinitely often must
+ * eventually fire.
+ */
+#[derive(Clone, Default)]
+pub struct Fairness {
+    pub weak: Vec<String>,
+    pub strong: Vec<String>,
+}
+
+impl Fairness {
+    pub fn new() -> Fairness {
+        Default::default()
+    }
+
+    pub fn weak(mut self, process: &str) -> Fairness {
+        self.weak.push(process.to_string());
+        self
+    }
+
+    pub fn strong(mut self, trans: &str) -> Fairness {
+        self.strong.push(trans.to_string());
+        self
+    }
+}
+
+/* synchronous product of an Lts with a Büchi automaton. terminal states
+ * of the Lts stutter forever so that finite runs are seen as infinite.
+ */
+pub(crate) struct Product {
+    /* (Lts state, automaton state) */
+    pub(crate) nodes: Vec<(StateId, usize)>,
+    /* (successor node, index into trans or None for stuttering) */
+    pub(crate) edges: Vec<Vec<(usize, Option<usize>)>>,
+    /* discovering edge of each node as (predecessor, trans) */
+    pub(crate) parent: Vec<Option<(usize, Option<usize>)>>,
+}
+
+/* what the loop of a lasso has to go through. a weakly fair process is
+ * met by a step of it or by a node where it is disabled.
+ */
+enum Goal {
+    Accept(usize),
+    Process(usize),
+    Trans(String),
+}
+
+fn same_trans(a: &CompTrans, b: &CompTrans) -> bool {
+    a.before == b.before && a.after == b.after && a.label == b.label
+}
+
+impl<T: std::fmt::Display + Clone + Eq + Hash, L: Local> Lts<T, L> {
+    pub(crate) fn product(&self, ba: &Buchi, preds: &[(&str, Predicate<T>)]) -> Product {
+        let ali = self.get_trans_ali();
+        let sat = |s: StateId, q: usize| {
+            ba.label[q]
+                .iter()
+                .all(|(a, pos)| self.eval_atom(a, s, preds) == *pos)
+        };
+
+        let mut index: HashMap<(StateId, usize), usize> = HashMap::new();
+        let mut p = Product {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            parent: Vec::new(),
+        };
+        let mut que = VecDeque::new();
+        for q in &ba.init {
+            if sat(0, *q) {
+                index.insert((0, *q), p.nodes.len());
+                que.push_back(p.nodes.len());
+                p.nodes.push((0, *q));
+                p.edges.push(vec![]);
+                p.parent.push(None);
+            }
+        }
+        while let Some(n) = que.pop_front() {
+            let (s, q) = p.nodes[n];
+            let mut moves: Vec<(StateId, Option<usize>)> = ali[s]
+                .iter()
+                .map(|i| (self.trans[*i].after, Some(*i)))
+                .collect();
+            if moves.is_empty() {
+                moves.push((s, None));
+            }
+            for (t, i) in moves {
+                for q2 in &ba.succ[q] {
+                    if !sat(t, *q2) {
+                        continue;
+                    }
+                    let m = match index.get(&(t, *q2)) {
+                        Some(m) => *m,
+                        None => {
+                            let m = p.nodes.len();
+                            index.insert((t, *q2), m);
+                            p.nodes.push((t, *q2));
+                            p.edges.push(vec![]);
+                            p.parent.push(Some((n, i)));
+                            que.push_back(m);
+                            m
+                        }
+                    };
+                    p.edges[n].push((m, i));
+                }
+            }
+        }
+        p
+    }
+
+    fn product_trans(&self, p: &Product, from: usize, i: Option<usize>) -> CompTrans {
+        match i {
+            Some(i) => self.trans[i].clone(),
+            None => {
+                let s = p.nodes[from].0;
+                CompTrans::new("stutter".to_string(), s, s)
+            }
+        }
+    }
+
+    /* shortest path inside the product from `from` ending with an edge
+     * that satisfies `goal`, taking only edges that satisfy `inside`
+     */
+    fn product_path(
+        &self,
+        p: &Product,
+        from: usize,
+        inside: &dyn Fn(usize, usize, Option<usize>) -> bool,
+        goal: &dyn Fn(usize, usize, Option<usize>) -> bool,
+    ) -> Option<(usize, Vec<CompTrans>)> {
+        let mut parent: HashMap<usize, (usize, Option<usize>)> = HashMap::new();
+        let mut que = VecDeque::new();
+        que.push_back(from);
+        while let Some(n) = que.pop_front() {
+            for (m, i) in &p.edges[n] {
+                if !inside(n, *m, *i) {
+                    continue;
+                }
+                if goal(n, *m, *i) {
+                    let mut path = vec![self.product_trans(p, n, *i)];
+                    let mut cur = n;
+                    while cur != from {
+                        let (prev, j) = parent[&cur];
+                        path.push(self.product_trans(p, prev, j));
+                        cur = prev;
+                    }
+                    path.reverse();
+                    return Some((*m, path));
+                }
+                if *m != from && !parent.contains_key(m) {
+                    parent.insert(*m, (n, *i));
+                    que.push_back(*m);
+                }
+            }
+        }
+        None
+    }
+
+    fn enabled(&self, ali: &[Vec<usize>], s: StateId, pid: usize) -> bool {
+        ali[s].iter().any(|i| self.trans[*i].involves(pid))
+    }
+
+    fn enabled_trans(&self, ali: &[Vec<usize>], s: StateId, label: &str) -> bool {
+        ali[s].iter().any(|i| self.trans[*i].label == label)
+    }
+
+    fn fires(&self, i: Option<usize>, pid: usize) -> bool {
+        i.map(|i| self.trans[i].involves(pid)).unwrap_or(false)
+    }
+
+    fn fires_trans(&self, i: Option<usize>, label: &str) -> bool {
+        i.map(|i| self.trans[i].label == label).unwrap_or(false)
+    }
+
+    /* strongly connected sets of product nodes, connected by `allowed`
+     * edges, that carry a run meeting every acceptance set and the
+     * fairness assumptions. strong fairness is handled by dropping the
+     * states that enable a neglected transition and searching again.
+     */
+    fn fair_components(
+        &self,
+        p: &Product,
+        accept: &[Vec<bool>],
+        allowed: &dyn Fn(Option<usize>) -> bool,
+        fair: &Fairness,
+    ) -> Result<Vec<Vec<usize>>, String> {
+        let weak: Vec<usize> = fair
+            .weak
+            .iter()
+            .map(|w| match self.labels.iter().position(|l| l.0 == *w) {
+                Some(pid) => Ok(pid),
+                None => Err(format!("unknown process {}", w)),
+            })
+            .collect::<Result<_, _>>()?;
+
+        let ali = self.get_trans_ali();
+        let mut ret = Vec::new();
+        let mut work: Vec<Vec<usize>> = vec![(0..p.nodes.len()).collect()];
+        while let Some(set) = work.pop() {
+            let mut local = vec![usize::MAX; p.nodes.len()];
+            for (k, n) in set.iter().enumerate() {
+                local[*n] = k;
+            }
+            let mut g = AdjacencyList::with_dimension(set.len());
+            for n in &set {
+                for (m, i) in &p.edges[*n] {
+                    if local[*m] != usize::MAX && allowed(*i) {
+                        g.insert(local[*n], local[*m]);
+                    }
+                }
+            }
+
+            for comp in g.scc() {
+                if !g.is_cyclic(&comp) {
+                    continue;
+                }
+                let comp: Vec<usize> = comp.iter().map(|k| set[*k]).collect();
+                let mut member = vec![false; p.nodes.len()];
+                for n in &comp {
+                    member[*n] = true;
+                }
+                let inner: Vec<Option<usize>> = comp
+                    .iter()
+                    .flat_map(|n| p.edges[*n].iter())
+                    .filter(|(m, i)| member[*m] && allowed(*i))
+                    .map(|(_, i)| *i)
+                    .collect();
+
+                if !accept.iter().all(|acc| comp.iter().any(|n| acc[*n])) {
+                    continue;
+                }
+                let starved = weak.iter().any(|pid| {
+                    comp.iter().all(|n| self.enabled(&ali, p.nodes[*n].0, *pid))
+                        && !inner.iter().any(|i| self.fires(*i, *pid))
+                });
+                if starved {
+                    continue;
+                }
+                let neglected: Vec<&String> = fair
+                    .strong
+                    .iter()
+                    .filter(|l| {
+                        comp.iter()
+                            .any(|n| self.enabled_trans(&ali, p.nodes[*n].0, l))
+                            && !inner.iter().any(|i| self.fires_trans(*i, l))
+                    })
+                    .collect();
+                if neglected.is_empty() {
+                    ret.push(comp);
+                } else {
+                    let rest: Vec<usize> = comp
+                        .into_iter()
+                        .filter(|n| {
+                            !neglected
+                                .iter()
+                                .any(|l| self.enabled_trans(&ali, p.nodes[*n].0, l))
+                        })
+                        .collect();
+                    if !rest.is_empty() {
+                        work.push(rest);
+                    }
+                }
+            }
+        }
+        ret.sort_by_key(|c| *c.iter().min().unwrap());
+        Ok(ret)
+    }
+
+    /* lasso through a fair component, entered at its earliest discovered
+     * node, whose loop meets every acceptance set and fairness obligation
+     */
+    fn product_lasso(
+        &self,
+        p: &Product,
+        accept: &[Vec<bool>],
+        allowed: &dyn Fn(Option<usize>) -> bool,
+        fair: &Fairness,
+        comp: &[usize],
+    ) -> Option<Lasso> {
+        let mut member = vec![false; p.nodes.len()];
+        for n in comp {
+            member[*n] = true;
+        }
+        let entry = *comp.iter().min().unwrap();
+        let ali = self.get_trans_ali();
+
+        let mut prefix = Vec::new();
+        let mut cur = entry;
+        while let Some((prev, i)) = p.parent[cur] {
+            prefix.push(self.product_trans(p, prev, i));
+            cur = prev;
+        }
+        prefix.reverse();
+
+        let mut goals: Vec<Goal> = (0..accept.len()).map(Goal::Accept).collect();
+        for w in &fair.weak {
+            if let Some(pid) = self.labels.iter().position(|l| l.0 == *w) {
+                goals.push(Goal::Process(pid));
+            }
+        }
+        for l in &fair.strong {
+            if comp
+                .iter()
+                .any(|n| self.enabled_trans(&ali, p.nodes[*n].0, l))
+            {
+                goals.push(Goal::Trans(l.clone()));
+            }
+        }
+
+        let inside = |_: usize, m: usize, i: Option<usize>| member[m] && allowed(i);
+        let mut cycle = Vec::new();
+        let mut cur = entry;
+        for g in &goals {
+            let met = match g {
+                Goal::Accept(k) => accept[*k][cur],
+                Goal::Process(pid) => !self.enabled(&ali, p.nodes[cur].0, *pid),
+                Goal::Trans(_) => false,
+            };
+            if met {
+                continue;
+            }
+            let goal = |_: usize, m: usize, i: Option<usize>| match g {
+                Goal::Accept(k) => accept[*k][m],
+                Goal::Process(pid) => {
+                    self.fires(i, *pid) || !self.enabled(&ali, p.nodes[m].0, *pid)
+                }
+                Goal::Trans(l) => self.fires_trans(i, l),
+            };
+            let (n, path) = self.product_path(p, cur, &inside, &goal)?;
+            cycle.extend(path);
+            cur = n;
+        }
+        if cur != entry || cycle.is_empty() {
+            let (_, path) = self.product_path(p, cur, &inside, &|_, m, _| m == entry)?;
+            cycle.extend(path);
+        }
+        Some(Lasso { prefix, cycle })
+    }
+
+    /* the acceptance sets of `ba` as sets of product nodes */
+    pub(crate) fn product_accept(&self, p: &Product, ba: &Buchi) -> Vec<Vec<bool>> {
+        ba.accept
+            .iter()
+            .map(|acc| p.nodes.iter().map(|(_, q)| acc[*q]).collect())
+            .collect()
+    }
+
+    /* lassos through fair cycles of `p` made of `allowed` edges whose
+     * loops meet every set of `accept`
+     */
+    pub(crate) fn fair_lassos(
+        &self,
+        p: &Product,
+        accept: &[Vec<bool>],
+        allowed: &dyn Fn(Option<usize>) -> bool,
+        fair: &Fairness,
+    ) -> Result<Vec<Lasso>, String> {
+        let comps = self.fair_components(p, accept, allowed, fair)?;
+        Ok(comps
+            .iter()
+            .filter_map(|c| self.product_lasso(p, accept, allowed, fair, c))
+            .collect())
+    }
+
+    /* one lasso for every reachable cycle that neither fires a progress
+     * transition nor passes a progress location
+     */
+    pub fn detect_livelock(&self) -> Vec<Lasso> {
+        self.detect_livelock_fair(&Fairness::new()).unwrap()
+    }
+
+    /* detect_livelock, leaving out cycles that break `fair` */
+    pub fn detect_livelock_fair(&self, fair: &Fairness) -> Result<Vec<Lasso>, String> {
+        let ba = Buchi::universal();
+        let p = self.product(&ba, &[]);
+        let allowed = |i: Option<usize>| match i {
+            Some(i) => {
+                let t = &self.trans[i];
+                !t.progress && !self.progress[t.before] && !self.progress[t.after]
+            }
+            None => false,
+        };
+        self.fair_lassos(&p, &[], &allowed, fair)
+    }
+
+    /* cycles on which `process` stays at the `waiting` locations while
+     * the others run. with no waiting locations given, the process must
+     * never fire yet be enabled somewhere on the cycle.
+     */
+    pub fn detect_starvation(
+        &self,
+        process: &str,
+        waiting: &[usize],
+        fair: &Fairness,
+    ) -> Result<Vec<Lasso>, String> {
+        let pid = match self.labels.iter().position(|l| l.0 == process) {
+            Some(pid) => pid,
+            None => return Err(format!("unknown process {}", process)),
+        };
+        let ba = Buchi::universal();
+        let p = self.product(&ba, &[]);
+        let ali = self.get_trans_ali();
+        let at = |s: StateId| {
+            let loc = self.state(s).unwrap().locations[pid];
+            waiting.contains(&loc.to_usize())
+        };
+        let allowed = |i: Option<usize>| match i {
+            Some(i) => {
+                let t = &self.trans[i];
+                if waiting.is_empty() {
+                    !t.involves(pid)
+                } else {
+                    at(t.before) && at(t.after)
+                }
+            }
+            None => false,
+        };
+        let accept: Vec<Vec<bool>> = if waiting.is_empty() {
+            vec![p
+                .nodes
+                .iter()
+                .map(|(s, _)| self.enabled(&ali, *s, pid))
+                .collect()]
+        } else {
+            vec![]
+        };
+        self.fair_lassos(&p, &accept, &allowed, fair)
+    }
+
+    /* detect_starvation for every process, without waiting locations */
+    pub fn detect_starvation_all(
+        &self,
+        fair: &Fairness,
+    ) -> Result<Vec<(Label, Vec<Lasso>)>, String> {
+        let mut ret = Vec::new();
+        for l in &self.labels {
+            ret.push((l.clone(), self.detect_starvation(&l.0, &[], fair)?));
+        }
+        Ok(ret)
+    }
+
+    pub fn mark_lasso(&mut self, lassos: &[Lasso]) {
+        for l in lassos {
+            for t in l.prefix.iter().chain(l.cycle.iter()) {
+                for e in self.trans.iter_mut() {
+                    if same_trans(e, t) {
+                        e.on_livelock = true;
+                    }
+                }
+            }
+        }
+    }
+}
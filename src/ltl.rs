@@ -0,0 +1,455 @@
+use crate::formula::*;
+use crate::liveness::{Fairness, Lasso};
+use crate::lts::*;
+use crate::process::Local;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::hash::Hash;
+use std::str::FromStr;
+
+/* linear temporal logic over the states of a composed Lts.
+ * syntax: `!`, `&&`, `||`, `->`, `X`, `F`, `G`, `U`, `R`, `true`, `false`,
+ * `P@2` and predicate names, e.g. `G(P@2 -> F P@0)`.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Ltl {
+    True,
+    False,
+    Ap(Atom),
+    Not(Box<Ltl>),
+    And(Box<Ltl>, Box<Ltl>),
+    Or(Box<Ltl>, Box<Ltl>),
+    Implies(Box<Ltl>, Box<Ltl>),
+    Next(Box<Ltl>),
+    Until(Box<Ltl>, Box<Ltl>),
+    Release(Box<Ltl>, Box<Ltl>),
+    Finally(Box<Ltl>),
+    Globally(Box<Ltl>),
+}
+
+impl fmt::Display for Ltl {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Ltl::True => write!(fmt, "true"),
+            Ltl::False => write!(fmt, "false"),
+            Ltl::Ap(a) => write!(fmt, "{}", a),
+            Ltl::Not(a) => write!(fmt, "!{}", a),
+            Ltl::And(a, b) => write!(fmt, "({} && {})", a, b),
+            Ltl::Or(a, b) => write!(fmt, "({} || {})", a, b),
+            Ltl::Implies(a, b) => write!(fmt, "({} -> {})", a, b),
+            Ltl::Next(a) => write!(fmt, "X {}", a),
+            Ltl::Until(a, b) => write!(fmt, "({} U {})", a, b),
+            Ltl::Release(a, b) => write!(fmt, "({} R {})", a, b),
+            Ltl::Finally(a) => write!(fmt, "F {}", a),
+            Ltl::Globally(a) => write!(fmt, "G {}", a),
+        }
+    }
+}
+
+impl FromStr for Ltl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ltl::parse(s)
+    }
+}
+
+/* an identifier made of X, F and G only is a chain of unary operators */
+fn is_unary_chain(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c == 'X' || c == 'F' || c == 'G')
+}
+
+impl Ltl {
+    pub fn parse(s: &str) -> Result<Ltl, String> {
+        let mut tokens = Tokens::new(s)?;
+        let ret = Ltl::parse_implies(&mut tokens)?;
+        if !tokens.is_end() {
+            return Err(format!("unexpected {:?}", tokens.peek().unwrap()));
+        }
+        Ok(ret)
+    }
+
+    fn parse_implies(tokens: &mut Tokens) -> Result<Ltl, String> {
+        let lhs = Ltl::parse_or(tokens)?;
+        if tokens.peek() == Some(&Token::Implies) {
+            tokens.next();
+            let rhs = Ltl::parse_implies(tokens)?;
+            return Ok(Ltl::Implies(Box::new(lhs), Box::new(rhs)));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_or(tokens: &mut Tokens) -> Result<Ltl, String> {
+        let mut lhs = Ltl::parse_and(tokens)?;
+        while tokens.peek() == Some(&Token::Or) {
+            tokens.next();
+            let rhs = Ltl::parse_and(tokens)?;
+            lhs = Ltl::Or(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_and(tokens: &mut Tokens) -> Result<Ltl, String> {
+        let mut lhs = Ltl::parse_until(tokens)?;
+        while tokens.peek() == Some(&Token::And) {
+            tokens.next();
+            let rhs = Ltl::parse_until(tokens)?;
+            lhs = Ltl::And(Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_until(tokens: &mut Tokens) -> Result<Ltl, String> {
+        let lhs = Ltl::parse_unary(tokens)?;
+        let op = match tokens.peek() {
+            Some(Token::Ident(s)) if s == "U" || s == "R" => s.clone(),
+            _ => return Ok(lhs),
+        };
+        tokens.next();
+        let rhs = Ltl::parse_until(tokens)?;
+        if op == "U" {
+            Ok(Ltl::Until(Box::new(lhs), Box::new(rhs)))
+        } else {
+            Ok(Ltl::Release(Box::new(lhs), Box::new(rhs)))
+        }
+    }
+
+    fn parse_unary(tokens: &mut Tokens) -> Result<Ltl, String> {
+        match tokens.next() {
+            Some(Token::Not) => Ok(Ltl::Not(Box::new(Ltl::parse_unary(tokens)?))),
+            Some(Token::LParen) => {
+                let ret = Ltl::parse_implies(tokens)?;
+                tokens.expect(Token::RParen)?;
+                Ok(ret)
+            }
+            Some(Token::At(l, loc)) => Ok(Ltl::Ap(Atom::At(l, loc))),
+            Some(Token::Ident(s)) => {
+                if s == "true" {
+                    Ok(Ltl::True)
+                } else if s == "false" {
+                    Ok(Ltl::False)
+                } else if is_unary_chain(&s) {
+                    let mut ret = Ltl::parse_unary(tokens)?;
+                    for c in s.chars().rev() {
+                        ret = match c {
+                            'X' => Ltl::Next(Box::new(ret)),
+                            'F' => Ltl::Finally(Box::new(ret)),
+                            _ => Ltl::Globally(Box::new(ret)),
+                        };
+                    }
+                    Ok(ret)
+                } else if s == "U" || s == "R" {
+                    Err(format!("missing left operand of {}", s))
+                } else {
+                    Ok(Ltl::Ap(Atom::Pred(s)))
+                }
+            }
+            Some(t) => Err(format!("unexpected {:?}", t)),
+            None => Err("unexpected end of formula".to_string()),
+        }
+    }
+
+    pub fn atoms(&self) -> Vec<Atom> {
+        let mut ret = Vec::new();
+        self.collect_atoms(&mut ret);
+        ret
+    }
+
+    fn collect_atoms(&self, v: &mut Vec<Atom>) {
+        match self {
+            Ltl::True | Ltl::False => {}
+            Ltl::Ap(a) => {
+                if !v.contains(a) {
+                    v.push(a.clone());
+                }
+            }
+            Ltl::Not(a) | Ltl::Next(a) | Ltl::Finally(a) | Ltl::Globally(a) => a.collect_atoms(v),
+            Ltl::And(a, b)
+            | Ltl::Or(a, b)
+            | Ltl::Implies(a, b)
+            | Ltl::Until(a, b)
+            | Ltl::Release(a, b) => {
+                a.collect_atoms(v);
+                b.collect_atoms(v);
+            }
+        }
+    }
+
+    /* negation normal form over true, false, literals, &&, ||, X, U and R */
+    pub(crate) fn nnf(&self, neg: bool) -> Ltl {
+        let b = Box::new;
+        match (self, neg) {
+            (Ltl::True, false) | (Ltl::False, true) => Ltl::True,
+            (Ltl::True, true) | (Ltl::False, false) => Ltl::False,
+            (Ltl::Ap(a), false) => Ltl::Ap(a.clone()),
+            (Ltl::Ap(a), true) => Ltl::Not(b(Ltl::Ap(a.clone()))),
+            (Ltl::Not(a), _) => a.nnf(!neg),
+            (Ltl::And(x, y), false) => Ltl::And(b(x.nnf(false)), b(y.nnf(false))),
+            (Ltl::And(x, y), true) => Ltl::Or(b(x.nnf(true)), b(y.nnf(true))),
+            (Ltl::Or(x, y), false) => Ltl::Or(b(x.nnf(false)), b(y.nnf(false))),
+            (Ltl::Or(x, y), true) => Ltl::And(b(x.nnf(true)), b(y.nnf(true))),
+            (Ltl::Implies(x, y), false) => Ltl::Or(b(x.nnf(true)), b(y.nnf(false))),
+            (Ltl::Implies(x, y), true) => Ltl::And(b(x.nnf(false)), b(y.nnf(true))),
+            (Ltl::Next(x), _) => Ltl::Next(b(x.nnf(neg))),
+            (Ltl::Until(x, y), false) => Ltl::Until(b(x.nnf(false)), b(y.nnf(false))),
+            (Ltl::Until(x, y), true) => Ltl::Release(b(x.nnf(true)), b(y.nnf(true))),
+            (Ltl::Release(x, y), false) => Ltl::Release(b(x.nnf(false)), b(y.nnf(false))),
+            (Ltl::Release(x, y), true) => Ltl::Until(b(x.nnf(true)), b(y.nnf(true))),
+            (Ltl::Finally(x), false) => Ltl::Until(b(Ltl::True), b(x.nnf(false))),
+            (Ltl::Finally(x), true) => Ltl::Release(b(Ltl::False), b(x.nnf(true))),
+            (Ltl::Globally(x), false) => Ltl::Release(b(Ltl::False), b(x.nnf(false))),
+            (Ltl::Globally(x), true) => Ltl::Until(b(Ltl::True), b(x.nnf(true))),
+        }
+    }
+
+    fn collect_until(&self, v: &mut Vec<Ltl>) {
+        match self {
+            Ltl::Until(a, b) => {
+                if !v.contains(self) {
+                    v.push(self.clone());
+                }
+                a.collect_until(v);
+                b.collect_until(v);
+            }
+            Ltl::Not(a) | Ltl::Next(a) | Ltl::Finally(a) | Ltl::Globally(a) => a.collect_until(v),
+            Ltl::And(a, b) | Ltl::Or(a, b) | Ltl::Implies(a, b) | Ltl::Release(a, b) => {
+                a.collect_until(v);
+                b.collect_until(v);
+            }
+            Ltl::True | Ltl::False | Ltl::Ap(_) => {}
+        }
+    }
+}
+
+/* tableau node of the Gerth-Peled-Vardi-Wolper construction */
+#[derive(Clone)]
+struct Node {
+    id: usize,
+    incoming: BTreeSet<usize>,
+    new: BTreeSet<Ltl>,
+    old: BTreeSet<Ltl>,
+    next: BTreeSet<Ltl>,
+}
+
+const INIT: usize = usize::MAX;
+
+fn expand(mut node: Node, nodes: &mut Vec<Node>, counter: &mut usize) {
+    let eta = match node.new.iter().next().cloned() {
+        Some(eta) => eta,
+        None => {
+            if let Some(nd) = nodes
+                .iter_mut()
+                .find(|nd| nd.old == node.old && nd.next == node.next)
+            {
+                nd.incoming.extend(node.incoming);
+                return;
+            }
+            let id = node.id;
+            let next = node.next.clone();
+            nodes.push(node);
+            *counter += 1;
+            let succ = Node {
+                id: *counter,
+                incoming: [id].iter().cloned().collect(),
+                new: next,
+                old: BTreeSet::new(),
+                next: BTreeSet::new(),
+            };
+            expand(succ, nodes, counter);
+            return;
+        }
+    };
+    node.new.remove(&eta);
+    if node.old.contains(&eta) {
+        expand(node, nodes, counter);
+        return;
+    }
+
+    match &eta {
+        Ltl::False => {}
+        Ltl::True => {
+            node.old.insert(eta);
+            expand(node, nodes, counter);
+        }
+        Ltl::Ap(_) | Ltl::Not(_) => {
+            let neg = match &eta {
+                Ltl::Not(a) => (**a).clone(),
+                _ => Ltl::Not(Box::new(eta.clone())),
+            };
+            if node.old.contains(&neg) {
+                return;
+            }
+            node.old.insert(eta);
+            expand(node, nodes, counter);
+        }
+        Ltl::And(a, b) => {
+            for x in [&**a, &**b].iter() {
+                if !node.old.contains(*x) {
+                    node.new.insert((*x).clone());
+                }
+            }
+            node.old.insert(eta.clone());
+            expand(node, nodes, counter);
+        }
+        Ltl::Next(a) => {
+            node.next.insert((**a).clone());
+            node.old.insert(eta.clone());
+            expand(node, nodes, counter);
+        }
+        Ltl::Or(a, b) | Ltl::Until(a, b) | Ltl::Release(a, b) => {
+            let (new1, next1, new2): (Vec<&Ltl>, Vec<&Ltl>, Vec<&Ltl>) = match &eta {
+                Ltl::Or(..) => (vec![a], vec![], vec![b]),
+                Ltl::Until(..) => (vec![a], vec![&eta], vec![b]),
+                _ => (vec![b], vec![&eta], vec![a, b]),
+            };
+            let mut old = node.old.clone();
+            old.insert(eta.clone());
+
+            let mut n1 = node.clone();
+            *counter += 1;
+            n1.id = *counter;
+            n1.old = old.clone();
+            for x in new1 {
+                if !node.old.contains(x) {
+                    n1.new.insert(x.clone());
+                }
+            }
+            for x in next1 {
+                n1.next.insert(x.clone());
+            }
+
+            let mut n2 = node.clone();
+            *counter += 1;
+            n2.id = *counter;
+            n2.old = old;
+            for x in new2 {
+                if !node.old.contains(x) {
+                    n2.new.insert(x.clone());
+                }
+            }
+
+            expand(n1, nodes, counter);
+            expand(n2, nodes, counter);
+        }
+        Ltl::Implies(..) | Ltl::Finally(_) | Ltl::Globally(_) => {
+            /* gone after nnf */
+            node.new.insert(eta.nnf(false));
+            expand(node, nodes, counter);
+        }
+    }
+}
+
+/* generalized Büchi automaton; state q reads a system state satisfying
+ * every literal in label[q]
+ */
+pub(crate) struct Buchi {
+    pub(crate) init: Vec<usize>,
+    pub(crate) succ: Vec<Vec<usize>>,
+    pub(crate) label: Vec<Vec<(Atom, bool)>>,
+    pub(crate) accept: Vec<Vec<bool>>,
+}
+
+impl Buchi {
+    /* a single state accepting every run */
+    pub(crate) fn universal() -> Buchi {
+        Buchi {
+            init: vec![0],
+            succ: vec![vec![0]],
+            label: vec![vec![]],
+            accept: vec![],
+        }
+    }
+
+    pub(crate) fn new(f: &Ltl) -> Buchi {
+        let f = f.nnf(false);
+        let mut nodes = Vec::new();
+        let mut counter = 0;
+        let start = Node {
+            id: 0,
+            incoming: [INIT].iter().cloned().collect(),
+            new: [f.clone()].iter().cloned().collect(),
+            old: BTreeSet::new(),
+            next: BTreeSet::new(),
+        };
+        expand(start, &mut nodes, &mut counter);
+
+        let index: HashMap<usize, usize> =
+            nodes.iter().enumerate().map(|(i, n)| (n.id, i)).collect();
+        let mut succ = vec![vec![]; nodes.len()];
+        let mut init = Vec::new();
+        for (i, n) in nodes.iter().enumerate() {
+            for src in &n.incoming {
+                if *src == INIT {
+                    init.push(i);
+                } else {
+                    succ[index[src]].push(i);
+                }
+            }
+        }
+        let label = nodes
+            .iter()
+            .map(|n| {
+                n.old
+                    .iter()
+                    .filter_map(|l| match l {
+                        Ltl::Ap(a) => Some((a.clone(), true)),
+                        Ltl::Not(x) => match &**x {
+                            Ltl::Ap(a) => Some((a.clone(), false)),
+                            _ => None,
+                        },
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut untils = Vec::new();
+        f.collect_until(&mut untils);
+        let accept = untils
+            .iter()
+            .map(|u| {
+                let rhs = match u {
+                    Ltl::Until(_, b) => (**b).clone(),
+                    _ => unreachable!(),
+                };
+                nodes
+                    .iter()
+                    .map(|n| !n.old.contains(u) || n.old.contains(&rhs))
+                    .collect()
+            })
+            .collect();
+
+        Buchi {
+            init,
+            succ,
+            label,
+            accept,
+        }
+    }
+}
+
+impl<T: std::fmt::Display + Clone + Eq + Hash, L: Local> Lts<T, L> {
+    /* None if every run satisfies `f`, otherwise a run violating it */
+    pub fn check_ltl(
+        &self,
+        f: &Ltl,
+        preds: &[(&str, Predicate<T>)],
+    ) -> Result<Option<Lasso>, String> {
+        self.check_ltl_fair(f, preds, &Fairness::new())
+    }
+
+    /* check_ltl over the runs that respect `fair` only */
+    pub fn check_ltl_fair(
+        &self,
+        f: &Ltl,
+        preds: &[(&str, Predicate<T>)],
+        fair: &Fairness,
+    ) -> Result<Option<Lasso>, String> {
+        self.check_atoms(&f.atoms(), preds)?;
+        let ba = Buchi::new(&Ltl::Not(Box::new(f.clone())));
+        let p = self.product(&ba, preds);
+        let accept = self.product_accept(&p, &ba);
+        let lassos = self.fair_lassos(&p, &accept, &|_| true, fair)?;
+        Ok(lassos.into_iter().next())
+    }
+}
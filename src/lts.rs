@@ -1,29 +1,49 @@
+use crate::channel::*;
+use crate::por::*;
 use crate::process::*;
+use crate::search::*;
+use crate::symmetry::*;
 use indexmap::IndexMap;
-use std::collections::VecDeque;
 use std::fmt;
 use std::fs;
 use std::hash::Hash;
 use std::io::{BufWriter, Write};
+use std::time::Instant;
 
 #[derive(Default, std::fmt::Debug, Clone, PartialEq, Eq, Hash)]
-pub struct State<T> {
+pub struct State<T, L = ()> {
     pub shared_vars: T,
     pub locations: Vec<Location>,
+    /* local variables of each process, in the order of the processes */
+    pub locals: Vec<L>,
+    /* buffered channels, in the order they were added */
+    pub channels: Vec<Queue>,
     pub deadlock: bool,
 }
 
-impl<T: std::fmt::Debug + Clone + Hash + Eq> State<T> {
-    pub fn new(r: T) -> State<T> {
+impl<T: std::fmt::Debug + Clone + Hash + Eq, L: Local> State<T, L> {
+    pub fn new(r: T) -> State<T, L> {
         State {
             shared_vars: r,
-            locations: vec![Location::new(0), Location::new(0)],
+            locations: Vec::new(),
+            locals: Vec::new(),
+            channels: Vec::new(),
             deadlock: false,
         }
     }
+
+    pub fn channel(mut self, q: Queue) -> State<T, L> {
+        self.channels.push(q);
+        self
+    }
+
+    /* local variables of the process numbered `pid` */
+    pub fn local(&self, pid: usize) -> &L {
+        &self.locals[pid]
+    }
 }
 
-impl<T: std::fmt::Debug + std::fmt::Display> fmt::Display for State<T> {
+impl<T: std::fmt::Debug + std::fmt::Display, L: Local> fmt::Display for State<T, L> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         write!(fmt, "{}", self.shared_vars)
     }
@@ -32,13 +52,13 @@ impl<T: std::fmt::Debug + std::fmt::Display> fmt::Display for State<T> {
 pub type StateId = usize;
 
 #[derive(Clone, PartialEq, Eq)]
-pub struct Trans<T> {
-    pub state: State<T>,
+pub struct Trans<T, L = ()> {
+    pub state: State<T, L>,
     pub dst: Vec<Option<(Label, Location)>>,
 }
 
-impl<T: Clone + Hash + Eq> Trans<T> {
-    pub fn new(s: &State<T>, v: Option<(Label, Location)>) -> Trans<T> {
+impl<T: Clone + Hash + Eq, L: Local> Trans<T, L> {
+    pub fn new(s: &State<T, L>, v: Option<(Label, Location)>) -> Trans<T, L> {
         Trans {
             state: s.clone(),
             dst: vec![v],
@@ -51,63 +71,223 @@ pub struct CompTrans {
     pub label: String,
     pub before: StateId,
     pub after: StateId,
+    /* index of the process that fired, the sender of a rendezvous */
+    pub process: usize,
+    /* the other processes moving along, the receiver of a rendezvous or
+     * those synchronizing on the label
+     */
+    pub partners: Vec<usize>,
+    /* the value passed over a channel */
+    pub msg: Option<Msg>,
     pub on_deadlock: bool,
+    pub on_livelock: bool,
+    /* fired by a progress ProcessTrans */
+    pub progress: bool,
 }
 
 impl CompTrans {
     pub fn new(label: String, b: StateId, a: StateId) -> CompTrans {
         CompTrans {
-            label: label,
+            label,
             before: b,
             after: a,
+            process: 0,
+            partners: Vec::new(),
+            msg: None,
             on_deadlock: false,
+            on_livelock: false,
+            progress: false,
         }
     }
+
+    /* the process numbered `pid` moves along this transition */
+    pub fn involves(&self, pid: usize) -> bool {
+        self.process == pid || self.partners.contains(&pid)
+    }
 }
 
+pub type Invariant<T, L = ()> = fn(&State<T, L>) -> bool;
+
+/* a state breaking a property, with a shortest path from the initial state */
 #[derive(Clone)]
-pub struct Lts<T> {
-    hat: IndexMap<State<T>, StateId>,
-    dead: IndexMap<State<T>, StateId>,
-    trans: Vec<CompTrans>,
+pub struct Violation {
+    pub state: StateId,
+    pub path: Vec<CompTrans>,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}:", self.state)?;
+        for t in &self.path {
+            write!(fmt, " {}", t.label)?;
+        }
+        Ok(())
+    }
 }
 
-impl<T: std::fmt::Display + Clone + Eq + Hash> Lts<T> {
-    pub fn new() -> Lts<T> {
+/* what went wrong at a state found by an on the fly check */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    Deadlock,
+    /* name of the invariant */
+    Invariant(String),
+    /* process and location of the assertion */
+    Assertion(Label, Location),
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::Deadlock => write!(fmt, "deadlock"),
+            Fault::Invariant(name) => write!(fmt, "invariant {} violated", name),
+            Fault::Assertion(l, loc) => write!(fmt, "assertion of {}{} failed", l, loc),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Lts<T, L = ()> {
+    pub(crate) hat: IndexMap<State<T, L>, StateId>,
+    pub(crate) dead: IndexMap<State<T, L>, StateId>,
+    pub(crate) trans: Vec<CompTrans>,
+    /* index into trans of the transition each state was discovered by */
+    pub(crate) parent: Vec<Option<usize>>,
+    /* every process sits in an end location */
+    pub(crate) end: Vec<bool>,
+    /* some process sits in a progress location */
+    pub(crate) progress: Vec<bool>,
+    /* per state result of a temporal query, set by mark_ctl */
+    pub(crate) verdict: Vec<Option<bool>>,
+    pub(crate) labels: Vec<Label>,
+    /* set when the exploration stopped at a SearchConfig bound */
+    pub(crate) partial: Option<Partial>,
+    /* found by the checks of SearchConfig, in the order they were met */
+    pub(crate) faults: Vec<(Fault, Violation)>,
+}
+
+impl<T: std::fmt::Display + Clone + Eq + Hash, L: Local> Default for Lts<T, L> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: std::fmt::Display + Clone + Eq + Hash, L: Local> Lts<T, L> {
+    pub fn new() -> Lts<T, L> {
         Lts {
             hat: IndexMap::new(),
             dead: IndexMap::new(),
             trans: Vec::new(),
+            parent: Vec::new(),
+            end: Vec::new(),
+            progress: Vec::new(),
+            verdict: Vec::new(),
+            labels: Vec::new(),
+            partial: None,
+            faults: Vec::new(),
         }
     }
 
-    fn make_states(&self) -> Vec<StateId> {
-        let mut v = Vec::new();
-        for e in self.trans.iter() {
-            v.push(e.after);
+    pub fn state(&self, id: StateId) -> Option<&State<T, L>> {
+        self.hat.get_index(id).map(|(k, _)| k)
+    }
+
+    pub fn len(&self) -> usize {
+        self.hat.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hat.is_empty()
+    }
+
+    pub fn trans(&self) -> &[CompTrans] {
+        &self.trans
+    }
+
+    pub fn faults(&self) -> &[(Fault, Violation)] {
+        &self.faults
+    }
+
+    pub fn is_partial(&self) -> bool {
+        self.partial.is_some()
+    }
+
+    pub fn partial(&self) -> Option<&Partial> {
+        self.partial.as_ref()
+    }
+
+    /* a state of the frontier left by a bounded exploration */
+    pub fn is_unexplored(&self, id: StateId) -> bool {
+        match &self.partial {
+            Some(p) => p.frontier.contains(&id),
+            None => false,
         }
-        v
     }
 
-    pub fn detect_deadlock(&self) -> (bool, Vec<StateId>) {
-        let mut deadlock = true;
-        let v = self.make_states();
-        let ret: Vec<StateId> = v
-            .clone()
-            .into_iter()
-            .filter(|x| {
-                for e in self.trans.iter() {
-                    if e.before == *x {
-                        return false;
-                    }
+    fn unexplored(&self) -> Vec<bool> {
+        let mut ret = vec![false; self.hat.len()];
+        if let Some(p) = &self.partial {
+            for id in &p.frontier {
+                ret[*id] = true;
+            }
+        }
+        ret
+    }
+
+    /* follow the parent pointers recorded while exploring; the path is
+     * a shortest one when the states were explored breadth first
+     */
+    pub fn shortest_path(&self, target: StateId) -> Option<Vec<CompTrans>> {
+        if target >= self.hat.len() {
+            return None;
+        }
+        let mut path = Vec::new();
+        let mut cur = target;
+        while let Some(i) = self.parent[cur] {
+            path.push(self.trans[i].clone());
+            cur = self.trans[i].before;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    pub fn check_invariant(&self, inv: Invariant<T, L>) -> Vec<Violation> {
+        let mut ret = Vec::new();
+        for (s, id) in self.hat.iter() {
+            if !inv(s) {
+                if let Some(path) = self.shortest_path(*id) {
+                    ret.push(Violation { state: *id, path });
                 }
-                true
-            })
+            }
+        }
+        ret
+    }
+
+    /* states of the unexplored frontier are neither deadlocked nor terminated */
+    pub fn detect_deadlock(&self) -> (bool, Vec<StateId>) {
+        let ali = self.get_ali();
+        let unexplored = self.unexplored();
+        let ret: Vec<StateId> = (0..ali.len())
+            .filter(|x| ali[*x].is_empty() && !self.end[*x] && !unexplored[*x])
             .collect();
-        if ret.len() == 0 {
-            deadlock = false;
+        (!ret.is_empty(), ret)
+    }
+
+    pub fn detect_termination(&self) -> Vec<StateId> {
+        let ali = self.get_ali();
+        let unexplored = self.unexplored();
+        (0..ali.len())
+            .filter(|x| ali[*x].is_empty() && self.end[*x] && !unexplored[*x])
+            .collect()
+    }
+
+    pub fn deadlocks(&self) -> Vec<Violation> {
+        let mut ret = Vec::new();
+        for d in self.detect_deadlock().1 {
+            if let Some(path) = self.shortest_path(d) {
+                ret.push(Violation { state: d, path });
+            }
         }
-        (deadlock, ret)
+        ret
     }
 
     pub fn mark_state(&mut self, deadlock: Vec<StateId>) {
@@ -133,56 +313,58 @@ impl<T: std::fmt::Display + Clone + Eq + Hash> Lts<T> {
     }
 
     pub fn mark_path(&mut self, deadlock: Vec<StateId>) {
-        let mut can: Vec<usize> = Vec::new();
-        for (i, t) in self.trans.iter().enumerate() {
-            for d in deadlock.iter() {
-                if t.after == *d {
-                    can.push(i);
-                    break;
-                }
-            }
-        }
-
-        let mut deadlock = Vec::new();
-        let mut suc = true;
-        for c in can {
-            let mut e = self.trans.remove(c);
-            e.on_deadlock = true;
-            deadlock.push(e.before);
-            self.trans.insert(c, e);
-            if c == 0 {
-                suc = false;
+        for d in deadlock {
+            let mut cur = d;
+            while let Some(i) = self.parent[cur] {
+                self.trans[i].on_deadlock = true;
+                cur = self.trans[i].before;
             }
         }
-
-        if suc == true {
-            self.mark_path(deadlock);
-        }
     }
 
     /* get a lts as adjacency-list representation */
     pub fn get_ali(&self) -> Vec<Vec<StateId>> {
-        let mut ret: Vec<Vec<StateId>> = vec![vec![]];
+        let mut ret: Vec<Vec<StateId>> = vec![vec![]; self.hat.len()];
         for e in &self.trans {
             ret[e.before].push(e.after);
         }
+        ret
+    }
 
-        for _e in &ret {
-            //println!("{}", );
+    /* same as get_ali, but each entry is an index into trans */
+    pub(crate) fn get_trans_ali(&self) -> Vec<Vec<usize>> {
+        let mut ret: Vec<Vec<usize>> = vec![vec![]; self.hat.len()];
+        for (i, e) in self.trans.iter().enumerate() {
+            ret[e.before].push(i);
         }
         ret
     }
 
     pub fn visualize(&self, path: &str) {
         let mut f = BufWriter::new(fs::File::create(path).unwrap());
+        let terminated = self.detect_termination();
+        let unexplored = self.unexplored();
         writeln!(f, "digraph {{").unwrap();
         for h in self.hat.iter() {
+            let mut locs = String::new();
+            let mut chans = String::new();
+            for (i, (l, loc)) in self.labels.iter().zip(h.0.locations.iter()).enumerate() {
+                locs.push_str(&format!("{}{}{} ", l, loc, h.0.locals[i].show()));
+            }
+            for q in &h.0.channels {
+                chans.push_str(&format!("\\n{}", q));
+            }
             write!(
                 f,
-                "{} [label=\"{}\\nP{} Q{} \\n{}\"",
-                h.1, h.1, h.0.locations[0], h.0.locations[1], h.0.shared_vars,
+                "{} [label=\"{}\\n{}\\n{}{}\"",
+                h.1, h.1, locs, h.0.shared_vars, chans,
             )
             .unwrap();
+            match self.verdict.get(*h.1) {
+                Some(Some(true)) => write!(f, "fontcolor=blue, ").unwrap(),
+                Some(Some(false)) => write!(f, "fontcolor=red, ").unwrap(),
+                _ => {}
+            }
             if *h.1 == 0 {
                 writeln!(f, "color=cyan, style=filled];").unwrap();
             } else {
@@ -194,8 +376,14 @@ impl<T: std::fmt::Display + Clone + Eq + Hash> Lts<T> {
                         break;
                     }
                 }
-                if deadlock == false {
-                    writeln!(f, "];").unwrap();
+                if !deadlock {
+                    if terminated.contains(h.1) {
+                        writeln!(f, "color=palegreen, style=filled];").unwrap();
+                    } else if unexplored[*h.1] {
+                        writeln!(f, "style=dashed];").unwrap();
+                    } else {
+                        writeln!(f, "];").unwrap();
+                    }
                 }
             }
         }
@@ -203,6 +391,8 @@ impl<T: std::fmt::Display + Clone + Eq + Hash> Lts<T> {
             write!(f, "{} -> {} [label=\"{}\"", v.before, v.after, v.label).unwrap();
             if v.on_deadlock {
                 writeln!(f, "color=red,fontcolor=red,weight=2,penwidth=2];").unwrap();
+            } else if v.on_livelock {
+                writeln!(f, "color=blue,fontcolor=blue,weight=2,penwidth=2];").unwrap();
             } else {
                 writeln!(f, "];").unwrap();
             }
@@ -211,55 +401,569 @@ impl<T: std::fmt::Display + Clone + Eq + Hash> Lts<T> {
     }
 }
 
-pub fn concurrent_composition<T: std::fmt::Display + Clone + Copy + Eq + Hash>(
-    process: Vec<Process<T>>,
-    s0: State<T>,
-) -> Lts<T> {
-    let mut lts = Lts::new();
-    let mut que: VecDeque<Trans<T>> = VecDeque::new();
-
-    let trans0 = Trans::new(&s0, None);
-    que.push_back(trans0);
-    lts.hat.insert(s0, 0);
-
-    loop {
-        if let Some(trans) = que.pop_front() {
-            let s = trans.state;
-            /* for each process */
-            for i in 0..process.len() {
-                let loc = s.locations[i].clone();
-                let pp = &process[i].v[loc.to_usize()];
-                for p in &pp.transs {
-                    if (p.guard)(process[i].prop, s.shared_vars) {
+/* one location per process, every process starts at location 0 with the
+ * local variables it was given
+ */
+pub(crate) fn init_state<T, L: Local>(process: &[Process<T, L>], s0: &mut State<T, L>) {
+    s0.locations.resize(process.len(), Location::new(0));
+    let given = s0.locals.len().min(process.len());
+    s0.locals
+        .extend(process[given..].iter().map(|p| p.init.clone()));
+}
+
+/* the invariants of `config` and the assertions of the current locations
+ * that `s` breaks
+ */
+pub(crate) fn state_faults<T: Copy, L: Local>(
+    process: &[Process<T, L>],
+    s: &State<T, L>,
+    config: &SearchConfig<T, L>,
+) -> Vec<Fault> {
+    let mut ret = Vec::new();
+    for (name, inv) in &config.invariants {
+        if !inv(s) {
+            ret.push(Fault::Invariant(name.clone()));
+        }
+    }
+    for (i, (p, loc)) in process.iter().zip(s.locations.iter()).enumerate() {
+        if let Some(a) = &p.v[loc.to_usize()].assertion {
+            if !a(
+                &Ctx {
+                    pid: i,
+                    prop: p.prop,
+                },
+                &s.shared_vars,
+                &s.locals[i],
+            ) {
+                ret.push(Fault::Assertion(p.label.clone(), *loc));
+            }
+        }
+    }
+    ret
+}
+
+pub(crate) fn is_end<T, L: Local>(process: &[Process<T, L>], s: &State<T, L>) -> bool {
+    process
+        .iter()
+        .zip(s.locations.iter())
+        .all(|(p, loc)| p.v[loc.to_usize()].end)
+}
+
+fn is_progress<T, L: Local>(process: &[Process<T, L>], s: &State<T, L>) -> bool {
+    process
+        .iter()
+        .zip(s.locations.iter())
+        .any(|(p, loc)| p.v[loc.to_usize()].progress)
+}
+
+/* a state reachable in one step, before it is numbered */
+pub(crate) struct Succ<T, L> {
+    pub(crate) process: usize,
+    pub(crate) partners: Vec<usize>,
+    pub(crate) msg: Option<Msg>,
+    pub(crate) label: String,
+    pub(crate) progress: bool,
+    pub(crate) state: State<T, L>,
+}
+
+/* index of the queue named `name` in `s` */
+fn queue<T, L>(s: &State<T, L>, name: &str) -> usize {
+    match s.channels.iter().position(|q| q.name == name) {
+        Some(c) => c,
+        None => panic!("unknown channel {}", name),
+    }
+}
+
+/* the states process ctx.pid reaches from `t` along `p`, with the label
+ * suffix of each choice
+ */
+fn act<T: Clone, L: Local>(
+    ctx: &Ctx,
+    p: &ProcessTrans<T, L>,
+    mut t: State<T, L>,
+) -> Vec<(String, State<T, L>)> {
+    t.locations[ctx.pid] = p.dst;
+    match &p.choice {
+        None => {
+            (p.action)(ctx, &mut t.shared_vars, &mut t.locals[ctx.pid]);
+            vec![(String::new(), t)]
+        }
+        Some(choose) => choose(ctx, &t.shared_vars, &t.locals[ctx.pid])
+            .into_iter()
+            .map(|(suffix, v, l)| {
+                let mut u = t.clone();
+                u.shared_vars = v;
+                u.locals[ctx.pid] = l;
+                (suffix, u)
+            })
+            .collect(),
+    }
+}
+
+/* the successors of `t` once process ctx.pid has moved along `p` alone */
+fn step<T: Clone, L: Local>(
+    ctx: &Ctx,
+    proc: &Process<T, L>,
+    p: &ProcessTrans<T, L>,
+    t: State<T, L>,
+    msg: Option<Msg>,
+    label: String,
+) -> Vec<Succ<T, L>> {
+    act(ctx, p, t)
+        .into_iter()
+        .map(|(suffix, t)| Succ {
+            process: ctx.pid,
+            partners: Vec::new(),
+            msg,
+            label: format!("{}.{}{}{}", proc.label, p.label, suffix, label),
+            progress: p.progress,
+            state: t,
+        })
+        .collect()
+}
+
+/* the processes firing a transition labelled `label` of process `pid`
+ * together, in the order of the processes
+ */
+fn participants<T, L>(process: &[Process<T, L>], pid: usize, label: &str) -> Vec<usize> {
+    if !process[pid].in_alphabet(label) {
+        return vec![pid];
+    }
+    (0..process.len())
+        .filter(|j| process[*j].in_alphabet(label))
+        .collect()
+}
+
+/* every way the processes of `parts` can fire a transition labelled like
+ * `p`, the one of the first, together. guards are evaluated in `s` and
+ * actions run in the order of the processes.
+ */
+fn synchronized<T: Clone, L: Local>(
+    process: &[Process<T, L>],
+    s: &State<T, L>,
+    parts: &[usize],
+    p: &ProcessTrans<T, L>,
+    ret: &mut Vec<Succ<T, L>>,
+) {
+    let mut combos: Vec<Vec<&ProcessTrans<T, L>>> = vec![vec![p]];
+    for j in &parts[1..] {
+        let ctx = Ctx {
+            pid: *j,
+            prop: process[*j].prop,
+        };
+        let enabled: Vec<&ProcessTrans<T, L>> = process[*j].v[s.locations[*j].to_usize()]
+            .transs
+            .iter()
+            .filter(|q| q.comm.is_none() && q.label == p.label)
+            .filter(|q| (q.guard)(&ctx, &s.shared_vars, &s.locals[*j]))
+            .collect();
+        combos = combos
+            .into_iter()
+            .flat_map(|c| {
+                enabled.iter().map(move |q| {
+                    let mut c = c.clone();
+                    c.push(*q);
+                    c
+                })
+            })
+            .collect();
+    }
+    for c in combos {
+        let mut outs: Vec<(Vec<String>, State<T, L>)> = vec![(Vec::new(), s.clone())];
+        for (j, q) in parts.iter().zip(c.iter()) {
+            let ctx = Ctx {
+                pid: *j,
+                prop: process[*j].prop,
+            };
+            outs = outs
+                .into_iter()
+                .flat_map(|(labels, t)| {
+                    act(&ctx, q, t).into_iter().map(move |(suffix, u)| {
+                        let mut labels = labels.clone();
+                        labels.push(format!("{}.{}{}", process[*j].label, q.label, suffix));
+                        (labels, u)
+                    })
+                })
+                .collect();
+        }
+        for (labels, t) in outs {
+            ret.push(Succ {
+                process: parts[0],
+                partners: parts[1..].to_vec(),
+                msg: None,
+                label: labels.join("|"),
+                progress: c.iter().any(|q| q.progress),
+                state: t,
+            });
+        }
+    }
+}
+
+/* fire every enabled transition of every process in `s`. a send fires
+ * with every enabled receive on its channel by another process, the
+ * value being computed before the step and the sender acting first.
+ * a put or a get also needs room in, or a message of, its queue.
+ * a transition on a label of its alphabet waits for the other processes
+ * sharing the label, see Process::alphabet.
+ */
+pub(crate) fn successors<T: Clone + Copy + Eq + Hash, L: Local>(
+    process: &[Process<T, L>],
+    s: &State<T, L>,
+) -> Vec<Succ<T, L>> {
+    let mut ret = Vec::new();
+    /* for each process */
+    for (i, proc) in process.iter().enumerate() {
+        let loc = s.locations[i];
+        let pp = &proc.v[loc.to_usize()];
+        let ctx = Ctx {
+            pid: i,
+            prop: proc.prop,
+        };
+        for p in &pp.transs {
+            if !(p.guard)(&ctx, &s.shared_vars, &s.locals[i]) {
+                continue;
+            }
+            let (chan, value) = match &p.comm {
+                None => {
+                    let parts = participants(process, i, &p.label.0);
+                    if parts.len() == 1 {
+                        ret.extend(step(&ctx, proc, p, s.clone(), None, String::new()));
+                    } else if parts[0] == i {
+                        synchronized(process, s, &parts, p, &mut ret);
+                    }
+                    continue;
+                }
+                Some(Comm::Put(chan, value)) => {
+                    let c = queue(s, chan);
+                    if s.channels[c].is_full() {
+                        continue;
+                    }
+                    let msg = value(&ctx, &s.shared_vars, &s.locals[i]);
+                    let mut t = s.clone();
+                    t.channels[c].msgs.push(msg);
+                    ret.extend(step(
+                        &ctx,
+                        proc,
+                        p,
+                        t,
+                        Some(msg),
+                        format!(" {}!{}", chan, msg),
+                    ));
+                    if s.channels[c].lossy {
+                        let label = format!(" {}!{} lost", chan, msg);
+                        ret.extend(step(&ctx, proc, p, s.clone(), Some(msg), label));
+                    }
+                    continue;
+                }
+                Some(Comm::Get(chan, store)) => {
+                    let c = queue(s, chan);
+                    for k in s.channels[c].takeable() {
                         let mut t = s.clone();
-                        t.locations[i] = p.dst;
-                        (p.action)(process[i].prop, &mut t.shared_vars, &s.shared_vars);
-                        let before_id = *lts.hat.get(&s).unwrap();
-                        let mut after_id = lts.hat.len();
-                        match lts.hat.get(&t) {
-                            None => {
-                                let trans = Trans::new(&t, Some((process[i].label.clone(), p.dst)));
-                                lts.hat.insert(t.clone(), after_id);
-                                que.push_back(trans);
-                            }
-                            Some(exist) => {
-                                after_id = *exist;
-                            }
+                        let msg = t.channels[c].msgs.remove(k);
+                        store(&ctx, &mut t.shared_vars, &mut t.locals[i], msg);
+                        ret.extend(step(
+                            &ctx,
+                            proc,
+                            p,
+                            t,
+                            Some(msg),
+                            format!(" {}?{}", chan, msg),
+                        ));
+                    }
+                    continue;
+                }
+                Some(Comm::Send(chan, value)) => (chan, value),
+                Some(Comm::Recv(..)) => continue,
+            };
+            let msg = value(&ctx, &s.shared_vars, &s.locals[i]);
+            for (j, other) in process.iter().enumerate().filter(|(j, _)| *j != i) {
+                let octx = Ctx {
+                    pid: j,
+                    prop: other.prop,
+                };
+                for q in &other.v[s.locations[j].to_usize()].transs {
+                    let store = match &q.comm {
+                        Some(Comm::Recv(c, store)) if c == chan => store,
+                        _ => continue,
+                    };
+                    if !(q.guard)(&octx, &s.shared_vars, &s.locals[j]) {
+                        continue;
+                    }
+                    for (ps, mut t) in act(&ctx, p, s.clone()) {
+                        store(&octx, &mut t.shared_vars, &mut t.locals[j], msg);
+                        for (qs, u) in act(&octx, q, t) {
+                            ret.push(Succ {
+                                process: i,
+                                partners: vec![j],
+                                msg: Some(msg),
+                                label: format!(
+                                    "{}.{}{}|{}.{}{} {}!{}",
+                                    proc.label, p.label, ps, other.label, q.label, qs, chan, msg
+                                ),
+                                progress: p.progress || q.progress,
+                                state: u,
+                            });
                         }
-                        let l = format!("{}.{}", process[i].label, p.label.clone());
-                        lts.trans.push(CompTrans::new(l, before_id, after_id));
                     }
                 }
             }
+        }
+    }
+    ret
+}
+
+impl<T: std::fmt::Display + Clone + Eq + Hash, L: Local> Lts<T, L> {
+    /* number a newly discovered state, reached by `parent` */
+    pub(crate) fn add_state(
+        &mut self,
+        process: &[Process<T, L>],
+        s: State<T, L>,
+        parent: Option<usize>,
+    ) -> StateId {
+        let id = self.hat.len();
+        self.end.push(is_end(process, &s));
+        self.progress.push(is_progress(process, &s));
+        self.hat.insert(s, id);
+        self.parent.push(parent);
+        id
+    }
+
+    pub(crate) fn fault(&mut self, fault: Fault, id: StateId) {
+        let path = self.shortest_path(id).unwrap();
+        self.faults.push((fault, Violation { state: id, path }));
+    }
+
+    /* invariants and assertions of a newly discovered state, true on a fault */
+    fn check_state(
+        &mut self,
+        process: &[Process<T, L>],
+        id: StateId,
+        config: &SearchConfig<T, L>,
+    ) -> bool
+    where
+        T: Copy,
+    {
+        let faults = state_faults(process, self.state(id).unwrap(), config);
+        let found = !faults.is_empty();
+        for f in faults {
+            self.fault(f, id);
+        }
+        found
+    }
+
+    pub(crate) fn finish(&mut self) {
+        let on_deadlock = self.detect_deadlock();
+        if on_deadlock.0 {
+            self.mark_path(on_deadlock.1.clone());
+            self.mark_state(on_deadlock.1);
+        }
+    }
+}
+
+pub fn concurrent_composition<T: std::fmt::Display + Clone + Copy + Eq + Hash, L: Local>(
+    process: Vec<Process<T, L>>,
+    s0: State<T, L>,
+) -> Lts<T, L> {
+    concurrent_composition_with(process, s0, &SearchConfig::new())
+}
+
+pub fn concurrent_composition_with<T: std::fmt::Display + Clone + Copy + Eq + Hash, L: Local>(
+    process: Vec<Process<T, L>>,
+    s0: State<T, L>,
+    config: &SearchConfig<T, L>,
+) -> Lts<T, L> {
+    let mut search = Search::start(&process, s0, config);
+    search.run(&process, config, None);
+    search.finish()
+}
+
+/* an exploration in progress: the states found so far and those left to
+ * expand, which checkpoint.rs saves and restores
+ */
+pub(crate) struct Search<T, L> {
+    pub(crate) lts: Lts<T, L>,
+    pub(crate) frontier: Frontier<T, L>,
+    /* distance of each state from the initial state, along its parent */
+    pub(crate) depth: Vec<usize>,
+    pub(crate) partial: Partial,
+    /* a bound was hit, nothing more gets expanded */
+    pub(crate) stopped: bool,
+    reduction: Option<Reduction>,
+    canon: Option<Canon<T>>,
+    start: Instant,
+}
+
+impl<T: std::fmt::Display + Clone + Copy + Eq + Hash, L: Local> Search<T, L> {
+    /* an exploration with no state yet, the caller fills lts and frontier in */
+    pub(crate) fn empty(process: &[Process<T, L>], config: &SearchConfig<T, L>) -> Search<T, L> {
+        assert!(
+            !config.reduction || config.invariants.is_empty() || !config.visible.is_empty(),
+            "invariants checked under reduction must list their variables in visible"
+        );
+        let mut lts = Lts::new();
+        lts.labels = process.iter().map(|p| p.label.clone()).collect();
+        Search {
+            lts,
+            frontier: Frontier::new(&config.strategy),
+            depth: Vec::new(),
+            partial: Partial {
+                limits: Vec::new(),
+                frontier: Vec::new(),
+            },
+            stopped: false,
+            reduction: if config.reduction {
+                Some(Reduction::new(process, &config.visible))
+            } else {
+                None
+            },
+            canon: if config.symmetry.is_empty() {
+                None
+            } else {
+                Some(Canon::new(process, &config.symmetry))
+            },
+            start: Instant::now(),
+        }
+    }
+
+    pub(crate) fn start(
+        process: &[Process<T, L>],
+        mut s0: State<T, L>,
+        config: &SearchConfig<T, L>,
+    ) -> Search<T, L> {
+        let mut search = Search::empty(process, config);
+
+        init_state(process, &mut s0);
+        if let Some(c) = &search.canon {
+            s0 = c.canonical(s0);
+        }
+        search.lts.add_state(process, s0, None);
+        search.depth.push(0);
+
+        if search.lts.check_state(process, 0, config) && config.stop_on_error {
+            search.partial.hit(Limit::Error);
+            search.partial.frontier.push(0);
+            search.stopped = true;
         } else {
-            break;
+            search.frontier.push(0, search.lts.state(0).unwrap());
+        }
+        search
+    }
+
+    /* expand up to `steps` states, or all of them. true once there is
+     * nothing left to expand
+     */
+    pub(crate) fn run(
+        &mut self,
+        process: &[Process<T, L>],
+        config: &SearchConfig<T, L>,
+        steps: Option<usize>,
+    ) -> bool {
+        let mut n = 0;
+        while !self.stopped && steps.is_none_or(|m| n < m) {
+            match self.frontier.pop() {
+                Some(id) => self.expand(process, config, id),
+                None => return true,
+            }
+            n += 1;
+        }
+        self.stopped || self.frontier.is_empty()
+    }
+
+    fn expand(
+        &mut self,
+        process: &[Process<T, L>],
+        config: &SearchConfig<T, L>,
+        before_id: StateId,
+    ) {
+        let elapsed = self.start.elapsed();
+        let depth = self.depth[before_id];
+        let lts = &mut self.lts;
+        let partial = &mut self.partial;
+        if config.time_limit.is_some_and(|t| elapsed >= t) {
+            partial.hit(Limit::Time);
+            partial.frontier.push(before_id);
+            self.stopped = true;
+            return;
+        }
+        let s = lts.state(before_id).unwrap().clone();
+        let mut succ = successors(process, &s);
+        if let Some(c) = &self.canon {
+            for n in succ.iter_mut() {
+                n.state = c.canonical(n.state.clone());
+            }
+        }
+        if config.deadlock && succ.is_empty() && !lts.end[before_id] {
+            lts.fault(Fault::Deadlock, before_id);
+            if config.stop_on_error {
+                partial.hit(Limit::Error);
+                self.stopped = true;
+                return;
+            }
+        }
+        if config.max_depth.is_some_and(|d| depth >= d) {
+            if !succ.is_empty() {
+                partial.hit(Limit::Depth);
+                partial.frontier.push(before_id);
+            }
+            return;
+        }
+        if let Some(i) = self.reduction.as_ref().and_then(|r| r.ample(&s, &succ)) {
+            /* cycle proviso: a state closing a cycle is expanded fully */
+            if succ
+                .iter()
+                .all(|n| n.process != i || !lts.hat.contains_key(&n.state))
+            {
+                succ.retain(|n| n.process == i);
+            }
+        }
+        self.frontier.shuffle(&mut succ);
+        let total = succ.len();
+        for (k, n) in succ.into_iter().enumerate() {
+            if config.max_trans.is_some_and(|m| lts.trans.len() >= m) {
+                partial.hit(Limit::Trans);
+                partial.frontier.push(before_id);
+                self.stopped = true;
+                return;
+            }
+            let (after_id, fresh) = match lts.hat.get(&n.state) {
+                Some(exist) => (*exist, false),
+                None => {
+                    if config.max_states.is_some_and(|m| lts.hat.len() >= m) {
+                        partial.hit(Limit::States);
+                        partial.frontier.push(before_id);
+                        self.stopped = true;
+                        return;
+                    }
+                    let parent = Some(lts.trans.len());
+                    self.depth.push(depth + 1);
+                    self.frontier.push(lts.hat.len(), &n.state);
+                    (lts.add_state(process, n.state, parent), true)
+                }
+            };
+            let mut ct = CompTrans::new(n.label, before_id, after_id);
+            ct.process = n.process;
+            ct.partners = n.partners;
+            ct.msg = n.msg;
+            ct.progress = n.progress;
+            lts.trans.push(ct);
+            if fresh && lts.check_state(process, after_id, config) && config.stop_on_error {
+                partial.hit(Limit::Error);
+                if k + 1 < total {
+                    partial.frontier.push(before_id);
+                }
+                self.stopped = true;
+                return;
+            }
         }
     }
-    let on_deadlock = lts.detect_deadlock();
-    if on_deadlock.0 {
-        lts.mark_path(on_deadlock.1.clone());
-        lts.mark_state(on_deadlock.1);
+
+    pub(crate) fn finish(mut self) -> Lts<T, L> {
+        if !self.partial.limits.is_empty() {
+            self.partial.frontier.extend(self.frontier.drain());
+            self.partial.frontier.sort_unstable();
+            self.lts.partial = Some(self.partial);
+        }
+        self.lts.finish();
+        self.lts
     }
-    //lts.get_ali();
-    lts
 }
@@ -0,0 +1,272 @@
+use crate::lts::*;
+use crate::process::*;
+use crate::search::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+/* a transition found by a worker, `order` being its position among the
+ * successors of `before`
+ */
+struct Edge {
+    before: StateId,
+    order: usize,
+    process: usize,
+    partners: Vec<usize>,
+    msg: Option<Msg>,
+    label: String,
+    progress: bool,
+    after: StateId,
+}
+
+/* what a worker brings back from one level: new states, transitions and
+ * the states a bound left half expanded
+ */
+type Level<T, L> = (
+    Vec<(StateId, State<T, L>)>,
+    Vec<Edge>,
+    Vec<(StateId, Limit)>,
+);
+
+/* take one from `count` unless it already reached `max` */
+fn take(count: &AtomicUsize, max: Option<usize>) -> Option<usize> {
+    count
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| match max {
+            Some(m) if n >= m => None,
+            _ => Some(n + 1),
+        })
+        .ok()
+}
+
+fn shard_of<T: Hash, L: Local>(s: &State<T, L>, n: usize) -> usize {
+    let mut h = DefaultHasher::new();
+    s.hash(&mut h);
+    (h.finish() % n as u64) as usize
+}
+
+/* renumber states, and the unexplored ones, in the order the sequential
+ * Bfs discovers them
+ */
+fn canonicalize<T, L: Local>(
+    states: Vec<State<T, L>>,
+    mut edges: Vec<Edge>,
+    unexplored: &mut [StateId],
+) -> (Vec<State<T, L>>, Vec<Edge>) {
+    let mut out: Vec<Vec<usize>> = vec![vec![]; states.len()];
+    for (i, e) in edges.iter().enumerate() {
+        out[e.before].push(i);
+    }
+    let mut renum = vec![usize::MAX; states.len()];
+    let mut next = 1;
+    let mut que = VecDeque::new();
+    renum[0] = 0;
+    que.push_back(0);
+    while let Some(s) = que.pop_front() {
+        for i in &out[s] {
+            let a = edges[*i].after;
+            if renum[a] == usize::MAX {
+                renum[a] = next;
+                next += 1;
+                que.push_back(a);
+            }
+        }
+    }
+
+    let mut slots: Vec<Option<State<T, L>>> = (0..states.len()).map(|_| None).collect();
+    for (id, s) in states.into_iter().enumerate() {
+        slots[renum[id]] = Some(s);
+    }
+    for e in edges.iter_mut() {
+        e.before = renum[e.before];
+        e.after = renum[e.after];
+    }
+    for id in unexplored.iter_mut() {
+        *id = renum[*id];
+    }
+    edges.sort_by_key(|e| (e.before, e.order));
+    (slots.into_iter().map(|s| s.unwrap()).collect(), edges)
+}
+
+/* level synchronous breadth first search. every level of the frontier is
+ * split among `config.threads` workers, and the visited states are
+ * spread over mutex protected shards by hash. state numbers depend on
+ * the scheduling unless `config.canonical` is set.
+ * max_states, max_depth and max_trans bound the result as they do for
+ * concurrent_composition_with, while time_limit is checked between
+ * levels. the on the fly checks, stop_on_error, the strategy, the
+ * reduction and the symmetry groups are not supported and rejected.
+ */
+pub fn concurrent_composition_parallel<T, L: Local + Send + Sync>(
+    process: Vec<Process<T, L>>,
+    mut s0: State<T, L>,
+    config: &SearchConfig<T, L>,
+) -> Lts<T, L>
+where
+    T: std::fmt::Display + Clone + Copy + Eq + Hash + Send + Sync,
+{
+    assert!(
+        config.invariants.is_empty() && !config.deadlock && !config.stop_on_error,
+        "on the fly checks are not supported in parallel"
+    );
+    assert!(
+        matches!(config.strategy, Strategy::Bfs),
+        "a parallel search is breadth first"
+    );
+    assert!(
+        !config.reduction && config.symmetry.is_empty(),
+        "reductions are not supported in parallel"
+    );
+    let start = Instant::now();
+    let threads = config.threads.max(1);
+    let nshard = threads * 4;
+    init_state(&process, &mut s0);
+
+    let shards: Vec<Mutex<HashMap<State<T, L>, StateId>>> =
+        (0..nshard).map(|_| Mutex::new(HashMap::new())).collect();
+    let next = AtomicUsize::new(1);
+    let ntrans = AtomicUsize::new(0);
+    shards[shard_of(&s0, nshard)]
+        .lock()
+        .unwrap()
+        .insert(s0.clone(), 0);
+
+    let mut frontier: Vec<(StateId, State<T, L>)> = vec![(0, s0)];
+    let mut edges: Vec<Edge> = Vec::new();
+    let mut partial = Partial {
+        limits: Vec::new(),
+        frontier: Vec::new(),
+    };
+    let mut depth = 0;
+    while !frontier.is_empty() {
+        if config.time_limit.is_some_and(|t| start.elapsed() >= t) {
+            partial.hit(Limit::Time);
+            partial.frontier.extend(frontier.iter().map(|(id, _)| *id));
+            break;
+        }
+        if config.max_depth.is_some_and(|d| depth >= d) {
+            for (id, s) in &frontier {
+                if !successors(&process, s).is_empty() {
+                    partial.hit(Limit::Depth);
+                    partial.frontier.push(*id);
+                }
+            }
+            break;
+        }
+        let chunk = frontier.len().div_ceil(threads);
+        let results: Vec<Level<T, L>> = thread::scope(|sc| {
+            let handles: Vec<_> = frontier
+                .chunks(chunk)
+                .map(|part| {
+                    let (process, shards, next, ntrans) = (&process, &shards, &next, &ntrans);
+                    sc.spawn(move || {
+                        let mut level = Vec::new();
+                        let mut found = Vec::new();
+                        let mut cut = Vec::new();
+                        for (id, s) in part {
+                            for (k, n) in successors(process, s).into_iter().enumerate() {
+                                if take(ntrans, config.max_trans).is_none() {
+                                    cut.push((*id, Limit::Trans));
+                                    break;
+                                }
+                                let mut m = shards[shard_of(&n.state, nshard)].lock().unwrap();
+                                let after = match m.get(&n.state) {
+                                    Some(a) => *a,
+                                    None => match take(next, config.max_states) {
+                                        Some(a) => {
+                                            m.insert(n.state.clone(), a);
+                                            level.push((a, n.state));
+                                            a
+                                        }
+                                        None => {
+                                            /* the transition is not kept either */
+                                            ntrans.fetch_sub(1, Ordering::SeqCst);
+                                            cut.push((*id, Limit::States));
+                                            break;
+                                        }
+                                    },
+                                };
+                                found.push(Edge {
+                                    before: *id,
+                                    order: k,
+                                    process: n.process,
+                                    partners: n.partners,
+                                    msg: n.msg,
+                                    label: n.label,
+                                    progress: n.progress,
+                                    after,
+                                });
+                            }
+                        }
+                        (level, found, cut)
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        frontier = Vec::new();
+        for (level, found, cut) in results {
+            frontier.extend(level);
+            edges.extend(found);
+            for (id, limit) in cut {
+                partial.hit(limit);
+                partial.frontier.push(id);
+            }
+        }
+        if !partial.limits.is_empty() {
+            /* a bound was hit, the next level is left unexplored */
+            partial.frontier.extend(frontier.iter().map(|(id, _)| *id));
+            break;
+        }
+        depth += 1;
+    }
+
+    let mut slots: Vec<Option<State<T, L>>> =
+        (0..next.load(Ordering::SeqCst)).map(|_| None).collect();
+    for shard in shards {
+        for (s, id) in shard.into_inner().unwrap() {
+            slots[id] = Some(s);
+        }
+    }
+    let states: Vec<State<T, L>> = slots.into_iter().map(|s| s.unwrap()).collect();
+    edges.sort_by_key(|e| (e.before, e.order));
+    let (states, edges) = if config.canonical {
+        canonicalize(states, edges, &mut partial.frontier)
+    } else {
+        (states, edges)
+    };
+
+    /* states of a level are numbered after those of the previous one, so
+     * the first transition into a state comes from its Bfs parent
+     */
+    let mut parent: Vec<Option<usize>> = vec![None; states.len()];
+    for (i, e) in edges.iter().enumerate() {
+        if e.after != 0 && parent[e.after].is_none() {
+            parent[e.after] = Some(i);
+        }
+    }
+
+    let mut lts = Lts::new();
+    lts.labels = process.iter().map(|p| p.label.clone()).collect();
+    for (id, s) in states.into_iter().enumerate() {
+        lts.add_state(&process, s, parent[id]);
+    }
+    for e in edges {
+        let mut ct = CompTrans::new(e.label, e.before, e.after);
+        ct.process = e.process;
+        ct.partners = e.partners;
+        ct.msg = e.msg;
+        ct.progress = e.progress;
+        lts.trans.push(ct);
+    }
+    if !partial.limits.is_empty() {
+        partial.frontier.sort_unstable();
+        partial.frontier.dedup();
+        lts.partial = Some(partial);
+    }
+    lts.finish();
+    lts
+}
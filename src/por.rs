@@ -0,0 +1,79 @@
+use crate::lts::*;
+use crate::process::*;
+
+/* static partial order reduction with ample sets.
+ * a process sitting at a location is safe when every transition leaving
+ * the location is independent of every transition of the other processes
+ * and invisible to the property, and none of them is a channel operation
+ * or synchronizes on its label, which moves another process too. the
+ * enabled transitions of a safe process then form an ample set: nothing
+ * the others do can enable, disable or be affected by them, so expanding
+ * them alone keeps every deadlock and every stutter invariant property.
+ * the cycle proviso is left to the caller, which must expand a state
+ * fully when an ample successor has already been discovered.
+ */
+pub(crate) struct Reduction {
+    safe: Vec<Vec<bool>>,
+}
+
+/* the sets share a variable, an undeclared set sharing with any non empty one */
+fn touches(a: &Option<Vec<String>>, b: &Option<Vec<String>>) -> bool {
+    match (a, b) {
+        (Some(a), _) if a.is_empty() => false,
+        (_, Some(b)) if b.is_empty() => false,
+        (Some(a), Some(b)) => a.iter().any(|v| b.contains(v)),
+        _ => true,
+    }
+}
+
+/* neither writes what the other reads or writes */
+fn independent<T, L: Local>(t: &ProcessTrans<T, L>, u: &ProcessTrans<T, L>) -> bool {
+    !touches(&t.writes, &u.reads) && !touches(&t.writes, &u.writes) && !touches(&u.writes, &t.reads)
+}
+
+/* another process shares the label with process `pid` */
+fn synchronized<T, L>(process: &[Process<T, L>], pid: usize, label: &str) -> bool {
+    process[pid].in_alphabet(label)
+        && process
+            .iter()
+            .enumerate()
+            .any(|(j, q)| j != pid && q.in_alphabet(label))
+}
+
+impl Reduction {
+    pub(crate) fn new<T, L: Local>(process: &[Process<T, L>], visible: &[String]) -> Reduction {
+        let observed = Some(visible.to_vec());
+        let mut safe = Vec::new();
+        for (i, p) in process.iter().enumerate() {
+            let shown = visible.contains(&p.label.0);
+            let v =
+                p.v.iter()
+                    .map(|unit| {
+                        !shown
+                            && !unit.transs.is_empty()
+                            && unit.transs.iter().all(|t| {
+                                t.comm.is_none()
+                                    && !synchronized(process, i, &t.label.0)
+                                    && !touches(&t.writes, &observed)
+                                    && process
+                                        .iter()
+                                        .enumerate()
+                                        .filter(|(j, _)| *j != i)
+                                        .flat_map(|(_, q)| q.v.iter())
+                                        .flat_map(|u| u.transs.iter())
+                                        .all(|u| independent(t, u))
+                            })
+                    })
+                    .collect();
+            safe.push(v);
+        }
+        Reduction { safe }
+    }
+
+    /* the process whose successors in `succ` form an ample set of `s` */
+    pub(crate) fn ample<T, L: Local>(&self, s: &State<T, L>, succ: &[Succ<T, L>]) -> Option<usize> {
+        (0..self.safe.len()).find(|i| {
+            self.safe[*i][s.locations[*i].to_usize()] && succ.iter().any(|n| n.process == *i)
+        })
+    }
+}
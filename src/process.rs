@@ -1,10 +1,79 @@
-use std::fs;
 use std::fmt;
+use std::fs;
+use std::hash::Hash;
 use std::io::{BufWriter, Write};
+use std::sync::Arc;
 
 pub type Prop = i32;
 pub type Guard<T> = fn(Prop, T) -> bool;
 pub type Action<T> = fn(Prop, &mut T, &T);
+/* the value passed by a rendezvous */
+pub type Msg = i32;
+
+/* what a guard or an action knows of the process firing it */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ctx {
+    /* index of the process in the composition */
+    pub pid: usize,
+    pub prop: Prop,
+}
+
+/* the variables of one process, kept in State next to the shared ones and
+ * seen only by the guards and actions of that process. `()` for processes
+ * without any.
+ */
+pub trait Local: Clone + Eq + Hash + fmt::Debug {
+    /* written after the location in dot labels */
+    fn show(&self) -> String;
+}
+
+impl Local for () {
+    fn show(&self) -> String {
+        String::new()
+    }
+}
+
+/* guards and actions as a ProcessTrans keeps them, over the shared and the
+ * local variables. closures may capture whatever a generated process
+ * needs, and are shared between the threads of
+ * concurrent_composition_parallel.
+ */
+pub type GuardFn<T, L = ()> = Arc<dyn Fn(&Ctx, &T, &L) -> bool + Send + Sync>;
+pub type ActionFn<T, L = ()> = Arc<dyn Fn(&Ctx, &mut T, &mut L) + Send + Sync>;
+
+pub fn guard_fn<T: Clone + 'static, L>(guard: Guard<T>) -> GuardFn<T, L> {
+    Arc::new(move |c: &Ctx, v: &T, _: &L| guard(c.prop, v.clone()))
+}
+
+/* the outcomes of a nondeterministic action: a label suffix and the
+ * shared and local variables after each choice
+ */
+pub type ChoiceFn<T, L = ()> = Arc<dyn Fn(&Ctx, &T, &L) -> Vec<(String, T, L)> + Send + Sync>;
+
+/* the value a send offers, and what a receive does with the value taken */
+pub type SendFn<T, L = ()> = Arc<dyn Fn(&Ctx, &T, &L) -> Msg + Send + Sync>;
+pub type RecvFn<T, L = ()> = Arc<dyn Fn(&Ctx, &mut T, &mut L, Msg) + Send + Sync>;
+
+/* a channel operation. a ProcessTrans carrying a Send or a Recv never
+ * fires alone: a send and a receive on the same synchronous channel by
+ * another process, both enabled, fire together as one step. Put and Get
+ * act on a channel::Queue of the state.
+ */
+#[derive(Clone)]
+pub enum Comm<T, L = ()> {
+    Send(String, SendFn<T, L>),
+    Recv(String, RecvFn<T, L>),
+    Put(String, SendFn<T, L>),
+    Get(String, RecvFn<T, L>),
+}
+
+/* the action sees the variables before the step next to those it updates */
+pub fn action_fn<T: Clone + 'static, L>(action: Action<T>) -> ActionFn<T, L> {
+    Arc::new(move |c: &Ctx, v: &mut T, _: &mut L| {
+        let before = v.clone();
+        action(c.prop, v, &before)
+    })
+}
 
 #[allow(dead_code)]
 pub fn guard_true<T: Clone + Eq>(_prop: Prop, _p: T) -> bool {
@@ -22,7 +91,7 @@ impl Location {
         Location(s)
     }
     pub fn to_usize(&self) -> usize {
-        self.0 as usize
+        self.0
     }
 }
 
@@ -48,52 +117,307 @@ impl fmt::Display for Label {
 }
 
 #[derive(Clone)]
-pub struct ProcessTrans<T> {
+pub struct ProcessTrans<T, L = ()> {
     pub label: Label,
     pub dst: Location,
-    pub guard: Guard<T>,
-    pub action: Action<T>,
+    pub guard: GuardFn<T, L>,
+    pub action: ActionFn<T, L>,
+    /* firing this transition counts as progress */
+    pub progress: bool,
+    /* names of the shared variables the guard and action read and the
+     * action writes, None when undeclared. used by partial order reduction,
+     * which takes an undeclared transition to touch every variable.
+     */
+    pub reads: Option<Vec<String>>,
+    pub writes: Option<Vec<String>>,
+    /* rendezvous this transition takes part in */
+    pub comm: Option<Comm<T, L>>,
+    /* when set the transition leads to every outcome, in place of action.
+     * no outcome at all leaves it disabled.
+     */
+    pub choice: Option<ChoiceFn<T, L>>,
 }
 
-impl<T: Clone + Eq> ProcessTrans<T> {
-    pub fn new(name: &str, dst: usize, guard: Guard<T>, action: Action<T>) -> ProcessTrans<T> {
+impl<T: Clone + Eq + 'static, L: 'static> ProcessTrans<T, L> {
+    pub fn new(name: &str, dst: usize, guard: Guard<T>, action: Action<T>) -> ProcessTrans<T, L> {
         ProcessTrans {
             label: Label::new(name),
             dst: Location::new(dst),
-            guard: guard,
-            action: action,
+            guard: guard_fn(guard),
+            action: action_fn(action),
+            progress: false,
+            reads: None,
+            writes: None,
+            comm: None,
+            choice: None,
         }
     }
+
+    pub fn new_closure<G, A>(name: &str, dst: usize, guard: G, action: A) -> ProcessTrans<T, L>
+    where
+        G: Fn(&Ctx, &T) -> bool + Send + Sync + 'static,
+        A: Fn(&Ctx, &mut T) + Send + Sync + 'static,
+    {
+        ProcessTrans::new_local(
+            name,
+            dst,
+            move |c: &Ctx, v: &T, _: &L| guard(c, v),
+            move |c: &Ctx, v: &mut T, _: &mut L| action(c, v),
+        )
+    }
+
+    /* guard and action over the local variables of the process as well */
+    pub fn new_local<G, A>(name: &str, dst: usize, guard: G, action: A) -> ProcessTrans<T, L>
+    where
+        G: Fn(&Ctx, &T, &L) -> bool + Send + Sync + 'static,
+        A: Fn(&Ctx, &mut T, &mut L) + Send + Sync + 'static,
+    {
+        ProcessTrans {
+            label: Label::new(name),
+            dst: Location::new(dst),
+            guard: Arc::new(guard),
+            action: Arc::new(action),
+            progress: false,
+            reads: None,
+            writes: None,
+            comm: None,
+            choice: None,
+        }
+    }
+
+    /* an action with several outcomes, see ChoiceFn */
+    pub fn new_choice<G, C>(name: &str, dst: usize, guard: G, choice: C) -> ProcessTrans<T, L>
+    where
+        G: Fn(&Ctx, &T, &L) -> bool + Send + Sync + 'static,
+        C: Fn(&Ctx, &T, &L) -> Vec<(String, T, L)> + Send + Sync + 'static,
+    {
+        let mut t = ProcessTrans::new_local(name, dst, guard, |_: &Ctx, _: &mut T, _: &mut L| {});
+        t.choice = Some(Arc::new(choice));
+        t
+    }
+
+    pub fn new_progress(
+        name: &str,
+        dst: usize,
+        guard: Guard<T>,
+        action: Action<T>,
+    ) -> ProcessTrans<T, L> {
+        ProcessTrans::new(name, dst, guard, action).progress()
+    }
+
+    /* firing this transition counts as progress, as new_progress does */
+    pub fn progress(mut self) -> ProcessTrans<T, L> {
+        self.progress = true;
+        self
+    }
+
+    pub fn access(mut self, reads: &[&str], writes: &[&str]) -> ProcessTrans<T, L> {
+        self.reads = Some(reads.iter().map(|v| v.to_string()).collect());
+        self.writes = Some(writes.iter().map(|v| v.to_string()).collect());
+        self
+    }
+
+    /* after the action, `assign` each of `values` in turn, one outcome
+     * labelled `(v)` per value
+     */
+    pub fn choose<A>(mut self, values: &[i32], assign: A) -> ProcessTrans<T, L>
+    where
+        L: Clone,
+        A: Fn(&Ctx, &mut T, &mut L, i32) + Send + Sync + 'static,
+    {
+        let values = values.to_vec();
+        let action = self.action.clone();
+        self.choice = Some(Arc::new(move |c: &Ctx, v: &T, l: &L| {
+            values
+                .iter()
+                .map(|x| {
+                    let (mut v, mut l) = (v.clone(), l.clone());
+                    action(c, &mut v, &mut l);
+                    assign(c, &mut v, &mut l, *x);
+                    (format!("({})", x), v, l)
+                })
+                .collect()
+        }));
+        self
+    }
+
+    /* offer the value computed before the step on `chan` */
+    pub fn send<F>(mut self, chan: &str, value: F) -> ProcessTrans<T, L>
+    where
+        F: Fn(&Ctx, &T, &L) -> Msg + Send + Sync + 'static,
+    {
+        self.comm = Some(Comm::Send(chan.to_string(), Arc::new(value)));
+        self
+    }
+
+    /* take a value from `chan`, `store` running before the action */
+    pub fn recv<F>(mut self, chan: &str, store: F) -> ProcessTrans<T, L>
+    where
+        F: Fn(&Ctx, &mut T, &mut L, Msg) + Send + Sync + 'static,
+    {
+        self.comm = Some(Comm::Recv(chan.to_string(), Arc::new(store)));
+        self
+    }
+
+    /* append the value computed before the step to the queue `chan` */
+    pub fn put<F>(mut self, chan: &str, value: F) -> ProcessTrans<T, L>
+    where
+        F: Fn(&Ctx, &T, &L) -> Msg + Send + Sync + 'static,
+    {
+        self.comm = Some(Comm::Put(chan.to_string(), Arc::new(value)));
+        self
+    }
+
+    /* take a message off the queue `chan`, `store` running before the action */
+    pub fn get<F>(mut self, chan: &str, store: F) -> ProcessTrans<T, L>
+    where
+        F: Fn(&Ctx, &mut T, &mut L, Msg) + Send + Sync + 'static,
+    {
+        self.comm = Some(Comm::Get(chan.to_string(), Arc::new(store)));
+        self
+    }
 }
 
 #[derive(Clone)]
-pub struct ExecUnit<T> {
+pub struct ExecUnit<T, L = ()> {
     pub src: Location,
-    pub transs: Vec<ProcessTrans<T>>,
+    pub transs: Vec<ProcessTrans<T, L>>,
+    /* the process may validly stop here */
+    pub end: bool,
+    /* reaching this location counts as progress */
+    pub progress: bool,
+    /* must hold whenever the process sits here */
+    pub assertion: Option<GuardFn<T, L>>,
 }
 
-impl<T: Clone + Eq> ExecUnit<T> {
-    pub fn new(src: usize, trans: Vec<ProcessTrans<T>>) -> Self {
+impl<T: Clone + Eq + 'static, L: 'static> ExecUnit<T, L> {
+    pub fn new(src: usize, trans: Vec<ProcessTrans<T, L>>) -> Self {
+        ExecUnit {
+            src: Location::new(src),
+            transs: trans,
+            end: false,
+            progress: false,
+            assertion: None,
+        }
+    }
+
+    pub fn new_end(src: usize, trans: Vec<ProcessTrans<T, L>>) -> Self {
+        ExecUnit {
+            src: Location::new(src),
+            transs: trans,
+            end: true,
+            progress: false,
+            assertion: None,
+        }
+    }
+
+    pub fn new_progress(src: usize, trans: Vec<ProcessTrans<T, L>>) -> Self {
         ExecUnit {
             src: Location::new(src),
             transs: trans,
+            end: false,
+            progress: true,
+            assertion: None,
         }
     }
+
+    pub fn assert(mut self, assertion: Guard<T>) -> Self {
+        self.assertion = Some(guard_fn(assertion));
+        self
+    }
+
+    pub fn assert_closure<A>(mut self, assertion: A) -> Self
+    where
+        A: Fn(&Ctx, &T) -> bool + Send + Sync + 'static,
+    {
+        self.assertion = Some(Arc::new(move |c: &Ctx, v: &T, _: &L| assertion(c, v)));
+        self
+    }
+
+    pub fn assert_local<A>(mut self, assertion: A) -> Self
+    where
+        A: Fn(&Ctx, &T, &L) -> bool + Send + Sync + 'static,
+    {
+        self.assertion = Some(Arc::new(assertion));
+        self
+    }
 }
 
 #[derive(Clone)]
-pub struct Process<T> {
+pub struct Process<T, L = ()> {
     pub label: Label,
-    pub v: Vec<ExecUnit<T>>,
+    pub v: Vec<ExecUnit<T, L>>,
     pub prop: Prop,
+    /* local variables the process starts with */
+    pub init: L,
+    /* labels the process synchronizes on, CSP style: a transition with one
+     * of them fires only together with one of the same label from every
+     * other process having it in its alphabet. None interleaves them all.
+     * channel operations are never synchronized by their label.
+     */
+    pub alphabet: Option<Vec<String>>,
 }
 
-impl<T: Clone> Process<T> {
-    pub fn new(label: &str, v: Vec<ExecUnit<T>>) -> Process<T> {
+impl<T: Clone, L: Default> Process<T, L> {
+    pub fn new(label: &str, v: Vec<ExecUnit<T, L>>) -> Process<T, L> {
         Process {
             label: Label::new(label),
-            v: v,
+            v,
             prop: 0,
+            init: Default::default(),
+            alphabet: None,
+        }
+    }
+
+    /* `n` instances of one template, labelled `label` followed by their
+     * index and given that index as prop. `def` builds the locations of
+     * an instance from its prop, which guards and actions see in Ctx too.
+     */
+    pub fn instances<F>(label: &str, n: usize, def: F) -> Vec<Process<T, L>>
+    where
+        F: Fn(Prop) -> Vec<ExecUnit<T, L>>,
+    {
+        (0..n as Prop)
+            .map(|i| Process::new(&format!("{}{}", label, i), def(i)).with_prop(i))
+            .collect()
+    }
+}
+
+impl<T, L> Process<T, L> {
+    pub fn with_prop(mut self, prop: Prop) -> Process<T, L> {
+        self.prop = prop;
+        self
+    }
+
+    pub fn with_init(mut self, init: L) -> Process<T, L> {
+        self.init = init;
+        self
+    }
+
+    /* synchronize on `labels`, which may include some the process never
+     * fires, so the others can never fire them either
+     */
+    pub fn alphabet(mut self, labels: &[&str]) -> Process<T, L> {
+        self.alphabet = Some(labels.iter().map(|l| l.to_string()).collect());
+        self
+    }
+
+    /* synchronize on every label of the process */
+    pub fn synchronize(mut self) -> Process<T, L> {
+        let mut labels: Vec<String> = Vec::new();
+        for t in self.v.iter().flat_map(|e| e.transs.iter()) {
+            if !labels.contains(&t.label.0) {
+                labels.push(t.label.0.clone());
+            }
+        }
+        self.alphabet = Some(labels);
+        self
+    }
+
+    pub fn in_alphabet(&self, label: &str) -> bool {
+        match &self.alphabet {
+            Some(a) => a.iter().any(|l| l == label),
+            None => false,
         }
     }
 
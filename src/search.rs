@@ -0,0 +1,315 @@
+use crate::codec::*;
+use crate::lts::*;
+use crate::store::*;
+use crate::symmetry::*;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+use std::time::Duration;
+
+/* estimated distance of a state to the states of interest, lower first */
+pub type Heuristic<T, L = ()> = fn(&State<T, L>) -> i64;
+
+/* order in which concurrent_composition expands states.
+ * Bfs numbers states by distance from the initial state and is what the
+ * golden dot files under ref/ are generated with.
+ */
+pub enum Strategy<T, L = ()> {
+    Bfs,
+    Dfs,
+    /* Dfs with the successors of each state shuffled, from a seed */
+    RandomDfs(u64),
+    BestFirst(Heuristic<T, L>),
+}
+
+impl<T, L> Clone for Strategy<T, L> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, L> Copy for Strategy<T, L> {}
+
+#[derive(Clone)]
+pub struct SearchConfig<T, L = ()> {
+    pub strategy: Strategy<T, L>,
+    /* worker threads of concurrent_composition_parallel */
+    pub threads: usize,
+    /* renumber a parallel result as the sequential Bfs would */
+    pub canonical: bool,
+    /* bounds of concurrent_composition_with, None is unbounded */
+    pub max_states: Option<usize>,
+    pub max_depth: Option<usize>,
+    pub max_trans: Option<usize>,
+    pub time_limit: Option<Duration>,
+    /* checked on the fly while exploring, see Lts::faults */
+    pub invariants: Vec<(String, Invariant<T, L>)>,
+    pub deadlock: bool,
+    /* stop exploring at the first fault */
+    pub stop_on_error: bool,
+    /* partial order reduction, see por.rs. the invariants are only sound
+     * when the variables they read are listed in visible, so checking
+     * invariants with an empty visible is rejected
+     */
+    pub reduction: bool,
+    /* process labels and variables the checked property observes */
+    pub visible: Vec<String>,
+    /* groups of identical processes, one state stored per orbit. paths
+     * are then only valid up to permutation, see Symmetry
+     */
+    pub symmetry: Vec<Symmetry<T>>,
+    /* visited set of store::coverage */
+    pub store: Store,
+}
+
+impl<T, L> Default for SearchConfig<T, L> {
+    fn default() -> Self {
+        SearchConfig {
+            strategy: Strategy::Bfs,
+            threads: 1,
+            canonical: false,
+            max_states: None,
+            max_depth: None,
+            max_trans: None,
+            time_limit: None,
+            invariants: Vec::new(),
+            deadlock: false,
+            stop_on_error: false,
+            reduction: false,
+            visible: Vec::new(),
+            symmetry: Vec::new(),
+            store: Store::Full,
+        }
+    }
+}
+
+impl<T, L> SearchConfig<T, L> {
+    pub fn new() -> SearchConfig<T, L> {
+        Default::default()
+    }
+
+    pub fn strategy(mut self, strategy: Strategy<T, L>) -> SearchConfig<T, L> {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn threads(mut self, threads: usize) -> SearchConfig<T, L> {
+        self.threads = threads;
+        self
+    }
+
+    pub fn canonical(mut self, canonical: bool) -> SearchConfig<T, L> {
+        self.canonical = canonical;
+        self
+    }
+
+    pub fn max_states(mut self, n: usize) -> SearchConfig<T, L> {
+        self.max_states = Some(n);
+        self
+    }
+
+    /* states further than `n` steps from the initial state are not expanded */
+    pub fn max_depth(mut self, n: usize) -> SearchConfig<T, L> {
+        self.max_depth = Some(n);
+        self
+    }
+
+    pub fn max_trans(mut self, n: usize) -> SearchConfig<T, L> {
+        self.max_trans = Some(n);
+        self
+    }
+
+    pub fn time_limit(mut self, t: Duration) -> SearchConfig<T, L> {
+        self.time_limit = Some(t);
+        self
+    }
+
+    pub fn invariant(mut self, name: &str, inv: Invariant<T, L>) -> SearchConfig<T, L> {
+        self.invariants.push((name.to_string(), inv));
+        self
+    }
+
+    pub fn deadlock(mut self, deadlock: bool) -> SearchConfig<T, L> {
+        self.deadlock = deadlock;
+        self
+    }
+
+    pub fn stop_on_error(mut self, stop: bool) -> SearchConfig<T, L> {
+        self.stop_on_error = stop;
+        self
+    }
+
+    pub fn reduction(mut self, reduction: bool) -> SearchConfig<T, L> {
+        self.reduction = reduction;
+        self
+    }
+
+    pub fn visible(mut self, names: &[&str]) -> SearchConfig<T, L> {
+        self.visible = names.iter().map(|n| n.to_string()).collect();
+        self
+    }
+
+    pub fn symmetric(mut self, group: &[&str], permute: Permute<T>) -> SearchConfig<T, L> {
+        self.symmetry.push(Symmetry::new(group, permute));
+        self
+    }
+
+    pub fn store(mut self, store: Store) -> SearchConfig<T, L> {
+        self.store = store;
+        self
+    }
+}
+
+/* a bound that cut the exploration short */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    States,
+    Depth,
+    Trans,
+    Time,
+    /* stop_on_error after the first fault */
+    Error,
+}
+
+/* what a bounded exploration left out.
+ * `frontier` holds the states whose successors were not (all) generated.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Partial {
+    pub limits: Vec<Limit>,
+    pub frontier: Vec<StateId>,
+}
+
+impl Partial {
+    pub(crate) fn hit(&mut self, limit: Limit) {
+        if !self.limits.contains(&limit) {
+            self.limits.push(limit);
+        }
+    }
+}
+
+/* states discovered but not expanded yet */
+pub(crate) struct Frontier<T, L> {
+    strategy: Strategy<T, L>,
+    deque: VecDeque<StateId>,
+    heap: BinaryHeap<(Reverse<i64>, Reverse<usize>, StateId)>,
+    seq: usize,
+    rng: u64,
+}
+
+impl<T, L> Frontier<T, L> {
+    pub(crate) fn new(strategy: &Strategy<T, L>) -> Frontier<T, L> {
+        let seed = match strategy {
+            Strategy::RandomDfs(seed) => *seed,
+            _ => 0,
+        };
+        Frontier {
+            strategy: *strategy,
+            deque: VecDeque::new(),
+            heap: BinaryHeap::new(),
+            seq: 0,
+            /* xorshift must not start from 0 */
+            rng: seed ^ 0x9e37_79b9_7f4a_7c15,
+        }
+    }
+
+    pub(crate) fn push(&mut self, id: StateId, s: &State<T, L>) {
+        match self.strategy {
+            Strategy::BestFirst(h) => {
+                self.heap.push((Reverse(h(s)), Reverse(self.seq), id));
+                self.seq += 1;
+            }
+            _ => self.deque.push_back(id),
+        }
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<StateId> {
+        match self.strategy {
+            Strategy::Bfs => self.deque.pop_front(),
+            Strategy::Dfs | Strategy::RandomDfs(_) => self.deque.pop_back(),
+            Strategy::BestFirst(_) => self.heap.pop().map(|(_, _, id)| id),
+        }
+    }
+
+    /* the order successors of one state get pushed in */
+    pub(crate) fn shuffle<X>(&mut self, v: &mut [X]) {
+        if let Strategy::RandomDfs(_) = self.strategy {
+            for i in (1..v.len()).rev() {
+                let j = (self.next_random() % (i as u64 + 1)) as usize;
+                v.swap(i, j);
+            }
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.deque.is_empty() && self.heap.is_empty()
+    }
+
+    /* which of the queues a strategy keeps its states in */
+    fn tag(strategy: &Strategy<T, L>) -> u8 {
+        match strategy {
+            Strategy::Bfs => 0,
+            Strategy::Dfs => 1,
+            Strategy::RandomDfs(_) => 2,
+            Strategy::BestFirst(_) => 3,
+        }
+    }
+
+    pub(crate) fn save(&self, out: &mut Vec<u8>) {
+        Frontier::tag(&self.strategy).encode(out);
+        let deque: Vec<StateId> = self.deque.iter().cloned().collect();
+        deque.encode(out);
+        self.heap.len().encode(out);
+        for (Reverse(h), Reverse(seq), id) in self.heap.iter() {
+            h.encode(out);
+            seq.encode(out);
+            id.encode(out);
+        }
+        self.seq.encode(out);
+        self.rng.encode(out);
+    }
+
+    /* None as well when the frontier was saved under another kind of
+     * strategy, or an id is not below `states`
+     */
+    pub(crate) fn load(
+        strategy: &Strategy<T, L>,
+        input: &mut &[u8],
+        states: usize,
+    ) -> Option<Frontier<T, L>> {
+        if u8::decode(input)? != Frontier::tag(strategy) {
+            return None;
+        }
+        let mut f = Frontier::new(strategy);
+        f.deque = Vec::<StateId>::decode(input)?.into_iter().collect();
+        if f.deque.iter().any(|id| *id >= states) {
+            return None;
+        }
+        for _ in 0..usize::decode(input)? {
+            let h = i64::decode(input)?;
+            let seq = usize::decode(input)?;
+            let id = usize::decode(input)?;
+            if id >= states {
+                return None;
+            }
+            f.heap.push((Reverse(h), Reverse(seq), id));
+        }
+        f.seq = usize::decode(input)?;
+        f.rng = u64::decode(input)?;
+        Some(f)
+    }
+
+    /* empty the frontier, in no particular order */
+    pub(crate) fn drain(&mut self) -> Vec<StateId> {
+        let mut ret: Vec<StateId> = self.deque.drain(..).collect();
+        ret.extend(self.heap.drain().map(|(_, _, id)| id));
+        ret
+    }
+
+    fn next_random(&mut self) -> u64 {
+        /* xorshift64* */
+        self.rng ^= self.rng >> 12;
+        self.rng ^= self.rng << 25;
+        self.rng ^= self.rng >> 27;
+        self.rng.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+}
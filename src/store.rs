@@ -0,0 +1,271 @@
+use crate::lts::*;
+use crate::process::*;
+use crate::search::*;
+use crate::symmetry::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/* how the states visited by `coverage` are remembered */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Store {
+    /* every state is kept, nothing is missed */
+    Full,
+    /* supertrace: a table of 2^log2_bits bits, `hashes` of them set per
+     * state. a state whose bits are all set already is taken as visited.
+     * log2_bits is kept within 1..=32, a table of 512MB at most, and
+     * within 1..=31 where usize is 32 bits wide.
+     */
+    Bitstate { log2_bits: u32, hashes: u32 },
+    /* hash compaction: a `bits` wide hash is kept per state */
+    Compact { bits: u32 },
+}
+
+enum Visited<T, L> {
+    Full(HashSet<State<T, L>>),
+    Bitstate {
+        table: Vec<u64>,
+        log2_bits: u32,
+        hashes: u32,
+        set: u64,
+    },
+    Compact {
+        seen: HashSet<u64>,
+        bits: u32,
+    },
+}
+
+fn hash_with<T: Hash, L: Local>(s: &State<T, L>, seed: u64) -> u64 {
+    let mut h = DefaultHasher::new();
+    seed.hash(&mut h);
+    s.hash(&mut h);
+    h.finish()
+}
+
+const MAX_LOG2_BITS: u32 = 32;
+
+fn mask(bits: u32) -> u64 {
+    if bits >= 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+impl<T: Clone + Eq + Hash, L: Local> Visited<T, L> {
+    fn new(store: Store) -> Visited<T, L> {
+        match store {
+            Store::Full => Visited::Full(HashSet::new()),
+            Store::Bitstate { log2_bits, hashes } => {
+                /* the bit count of the table has to fit a usize */
+                let log2_bits = log2_bits.clamp(1, MAX_LOG2_BITS.min(usize::BITS - 1));
+                Visited::Bitstate {
+                    table: vec![0; (1usize << log2_bits).div_ceil(64)],
+                    log2_bits,
+                    hashes: hashes.max(1),
+                    set: 0,
+                }
+            }
+            Store::Compact { bits } => Visited::Compact {
+                seen: HashSet::new(),
+                bits: bits.clamp(1, 64),
+            },
+        }
+    }
+
+    /* chance that a state never seen before is taken as visited */
+    fn false_match(&self) -> f64 {
+        match self {
+            Visited::Full(_) => 0.0,
+            Visited::Bitstate {
+                log2_bits,
+                hashes,
+                set,
+                ..
+            } => (*set as f64 / (*log2_bits as f64).exp2()).powi(*hashes as i32),
+            Visited::Compact { seen, bits } => seen.len() as f64 / (mask(*bits) as f64 + 1.0),
+        }
+    }
+
+    /* true when `s` was not visited yet */
+    fn insert(&mut self, s: &State<T, L>) -> bool {
+        match self {
+            Visited::Full(seen) => {
+                if seen.contains(s) {
+                    false
+                } else {
+                    seen.insert(s.clone());
+                    true
+                }
+            }
+            Visited::Bitstate {
+                table,
+                log2_bits,
+                hashes,
+                set,
+            } => {
+                /* double hashing, h1 + i * h2 */
+                let h1 = hash_with(s, 0);
+                let h2 = hash_with(s, 1) | 1;
+                let mut fresh = false;
+                for i in 0..*hashes as u64 {
+                    let b = h1.wrapping_add(i.wrapping_mul(h2)) & mask(*log2_bits);
+                    let (w, bit) = ((b / 64) as usize, 1u64 << (b % 64));
+                    if table[w] & bit == 0 {
+                        table[w] |= bit;
+                        *set += 1;
+                        fresh = true;
+                    }
+                }
+                fresh
+            }
+            Visited::Compact { seen, bits } => seen.insert(hash_with(s, 0) & mask(*bits)),
+        }
+    }
+}
+
+/* a fault met by `coverage`, with the labels of the transitions leading to it */
+#[derive(Clone)]
+pub struct Counterexample<T, L = ()> {
+    pub fault: Fault,
+    pub state: State<T, L>,
+    pub path: Vec<String>,
+}
+
+impl<T, L: Local> fmt::Display for Counterexample<T, L> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}:", self.fault)?;
+        for l in &self.path {
+            write!(fmt, " {}", l)?;
+        }
+        Ok(())
+    }
+}
+
+/* what an approximate run went through. `omitted` estimates the number of
+ * reachable states lost to hash collisions, summing for every stored state
+ * the chance it would have been taken as visited; `omission` is the share
+ * of the reachable states that estimate stands for.
+ */
+pub struct Coverage<T, L = ()> {
+    pub states: usize,
+    pub trans: usize,
+    /* successors found visited already. Bitstate and Compact keep no
+     * states, so a revisit cannot be told from a hash collision and the
+     * two are not counted apart; every state lost to a collision shows up
+     * here, and `omitted` estimates how many did.
+     */
+    pub hits: usize,
+    pub depth: usize,
+    pub omitted: f64,
+    pub omission: f64,
+    pub faults: Vec<Counterexample<T, L>>,
+}
+
+impl<T, L: Local> fmt::Display for Coverage<T, L> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "{} states, {} transitions, {} hits, depth {}, omission probability {:.3e}",
+            self.states, self.trans, self.hits, self.depth, self.omission
+        )
+    }
+}
+
+/* a state on the search stack and the successors left to try */
+struct Frame<T, L> {
+    label: Option<String>,
+    succ: Vec<Succ<T, L>>,
+}
+
+/* depth first search keeping only `config.store` of the visited states,
+ * so no Lts is built. the checks of `config` are run on every new state,
+ * as are its symmetry groups, stop_on_error and max_depth; the other
+ * bounds, the strategy and the reduction are not used.
+ */
+pub fn coverage<T: std::fmt::Display + Clone + Copy + Eq + Hash, L: Local>(
+    process: Vec<Process<T, L>>,
+    mut s0: State<T, L>,
+    config: &SearchConfig<T, L>,
+) -> Coverage<T, L> {
+    init_state(&process, &mut s0);
+    let canon = if config.symmetry.is_empty() {
+        None
+    } else {
+        Some(Canon::new(&process, &config.symmetry))
+    };
+    if let Some(c) = &canon {
+        s0 = c.canonical(s0);
+    }
+
+    let mut visited = Visited::new(config.store);
+    let mut cov = Coverage {
+        states: 0,
+        trans: 0,
+        hits: 0,
+        depth: 0,
+        omitted: 0.0,
+        omission: 0.0,
+        faults: Vec::new(),
+    };
+    let mut stack: Vec<Frame<T, L>> = Vec::new();
+    visited.insert(&s0);
+    let mut next = Some((None, s0));
+    while let Some((label, s)) = next.take() {
+        cov.states += 1;
+        cov.depth = cov.depth.max(stack.len());
+
+        let cut = config.max_depth.is_some_and(|d| stack.len() >= d);
+        let mut succ = if cut {
+            Vec::new()
+        } else {
+            successors(&process, &s)
+        };
+        let mut faults = state_faults(&process, &s, config);
+        if config.deadlock && !cut && succ.is_empty() && !is_end(&process, &s) {
+            faults.push(Fault::Deadlock);
+        }
+        if !faults.is_empty() {
+            let mut path: Vec<String> = stack.iter().filter_map(|f| f.label.clone()).collect();
+            path.extend(label.clone());
+            for fault in faults {
+                cov.faults.push(Counterexample {
+                    fault,
+                    state: s.clone(),
+                    path: path.clone(),
+                });
+            }
+            if config.stop_on_error {
+                break;
+            }
+        }
+        if let Some(c) = &canon {
+            for n in succ.iter_mut() {
+                n.state = c.canonical(n.state.clone());
+            }
+        }
+        succ.reverse();
+        stack.push(Frame { label, succ });
+
+        while let Some(top) = stack.last_mut() {
+            match top.succ.pop() {
+                Some(n) => {
+                    cov.trans += 1;
+                    let p = visited.false_match();
+                    if visited.insert(&n.state) {
+                        cov.omitted += p;
+                        next = Some((Some(n.label), n.state));
+                        break;
+                    }
+                    cov.hits += 1;
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+    }
+    cov.omission = cov.omitted / (cov.states as f64 + cov.omitted);
+    cov
+}
@@ -0,0 +1,130 @@
+use crate::lts::*;
+use crate::process::*;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/* move the per-process fields of a symmetric group around:
+ * member i of the result gets the fields member perm[i] had
+ */
+pub type Permute<T> = fn(&T, &[usize]) -> T;
+
+/* processes, named by label, that are identical up to the fields
+ * `permute` moves around. members must be built from the same definition
+ * and differ only in their label. guards and actions see the prop, so
+ * members with different props, as Process::instances gives them, are
+ * rejected.
+ * a transition of a reduced run keeps the label of the step taken from
+ * its source, but leads to the representative of the state that step
+ * reaches. its paths and counterexamples hold only up to a permutation
+ * of the members after every step: "P.TAS" may arrive where R holds the
+ * lock.
+ */
+#[derive(Clone)]
+pub struct Symmetry<T> {
+    pub group: Vec<String>,
+    pub permute: Permute<T>,
+}
+
+impl<T> Symmetry<T> {
+    pub fn new(group: &[&str], permute: Permute<T>) -> Symmetry<T> {
+        Symmetry {
+            group: group.iter().map(|l| l.to_string()).collect(),
+            permute,
+        }
+    }
+}
+
+/* every permutation of 0..n, in lexicographic order */
+fn permutations(n: usize) -> Vec<Vec<usize>> {
+    let mut p: Vec<usize> = (0..n).collect();
+    let mut ret = vec![p.clone()];
+    loop {
+        let i = match (1..n).rev().find(|i| p[i - 1] < p[*i]) {
+            Some(i) => i - 1,
+            None => return ret,
+        };
+        let j = (i + 1..n).rev().find(|j| p[i] < p[*j]).unwrap();
+        p.swap(i, j);
+        p[i + 1..].reverse();
+        ret.push(p.clone());
+    }
+}
+
+/* maps a state to the representative of its orbit: the permutation with
+ * the least locations, ties broken by the hash of the variables. local
+ * variables move with the locations of their process.
+ * only the choice of representative depends on the order; any member of
+ * the orbit would be sound.
+ */
+pub(crate) struct Canon<T> {
+    groups: Vec<(Vec<usize>, Permute<T>)>,
+    perms: Vec<Vec<Vec<usize>>>,
+}
+
+fn key<T: Hash, L: Local>(s: &State<T, L>) -> (Vec<usize>, u64) {
+    let mut h = DefaultHasher::new();
+    s.shared_vars.hash(&mut h);
+    s.locals.hash(&mut h);
+    (
+        s.locations.iter().map(|l| l.to_usize()).collect(),
+        h.finish(),
+    )
+}
+
+impl<T: Clone + Hash> Canon<T> {
+    /* panics when a group names an unknown process, or members that
+     * differ in their prop or number of locations
+     */
+    pub(crate) fn new<L>(process: &[Process<T, L>], sym: &[Symmetry<T>]) -> Canon<T> {
+        let groups: Vec<(Vec<usize>, Permute<T>)> = sym
+            .iter()
+            .map(|g| {
+                let ids: Vec<usize> = g
+                    .group
+                    .iter()
+                    .map(|l| match process.iter().position(|p| &p.label.0 == l) {
+                        Some(i) => i,
+                        None => panic!("unknown process {} in symmetric group", l),
+                    })
+                    .collect();
+                for w in ids.windows(2) {
+                    let (p, q) = (&process[w[0]], &process[w[1]]);
+                    assert!(
+                        p.prop == q.prop && p.v.len() == q.v.len(),
+                        "{} and {} in a symmetric group are not identical",
+                        p.label.0,
+                        q.label.0
+                    );
+                }
+                (ids, g.permute)
+            })
+            .collect();
+        let perms = groups
+            .iter()
+            .map(|(ids, _)| permutations(ids.len()))
+            .collect();
+        Canon { groups, perms }
+    }
+
+    pub(crate) fn canonical<L: Local>(&self, s: State<T, L>) -> State<T, L> {
+        let mut best = s;
+        for ((ids, permute), perms) in self.groups.iter().zip(self.perms.iter()) {
+            let base = best.clone();
+            let mut best_key = key(&best);
+            for perm in perms.iter().skip(1) {
+                let mut c = base.clone();
+                for (i, p) in perm.iter().enumerate() {
+                    c.locations[ids[i]] = base.locations[ids[*p]];
+                    c.locals[ids[i]] = base.locals[ids[*p]].clone();
+                }
+                c.shared_vars = permute(&base.shared_vars, perm);
+                let k = key(&c);
+                if k < best_key {
+                    best = c;
+                    best_key = k;
+                }
+            }
+        }
+        best
+    }
+}